@@ -0,0 +1,597 @@
+//! Browsing inside ZIP, ARC, LZH and ZOO archives
+//!
+//! Members are addressed as `<archive url>!/<path>`, and directories
+//! inside an archive as `<archive url>!/<dir>/`. Archives can nest.
+
+use crate::error::LoadError;
+use crate::parser::DirEntry;
+use anyhow::{bail, Context, Result};
+use std::io::{Cursor, Read};
+
+/// Separates an archive's URL from the path of a member inside it
+pub const SEPARATOR: &str = "!/";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Zip,
+    Arc,
+    Lzh,
+    Zoo,
+}
+
+pub struct Member {
+    /// Path inside the archive, `/`-separated
+    pub path: String,
+    pub size: u64,
+    /// Compression method, for formats where only some can be extracted
+    pub method: Option<String>,
+    pub supported: bool,
+    pub is_dir: bool,
+}
+
+/// Split `url` into the archive it points into and the member path.
+pub fn split_url(url: &str) -> Option<(&str, &str)> {
+    url.rfind(SEPARATOR).map(|i| (&url[..i], &url[i + SEPARATOR.len()..]))
+}
+
+impl Format {
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Zip => "ZIP",
+            Format::Arc => "ARC",
+            Format::Lzh => "LZH",
+            Format::Zoo => "ZOO",
+        }
+    }
+}
+
+pub fn detect(bytes: &[u8]) -> Option<Format> {
+    if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+        Some(Format::Zip)
+    } else if bytes.len() > 24 && bytes[20..24] == ZOO_MAGIC.to_le_bytes() {
+        Some(Format::Zoo)
+    } else if is_lzh(bytes) {
+        Some(Format::Lzh)
+    } else if bytes.len() > 29 && bytes[0] == 0x1a && (1..=9).contains(&bytes[1]) {
+        Some(Format::Arc)
+    } else {
+        None
+    }
+}
+
+/// Whether `bytes` start with an LZH header: a `-l??-` method, a known
+/// level and, for levels 0 and 1, the checksum over the header. The
+/// method alone turns up in text too often.
+fn is_lzh(bytes: &[u8]) -> bool {
+    if !bytes.get(2..7).is_some_and(|m| m.starts_with(b"-l") && m[4] == b'-') {
+        return false;
+    }
+    match bytes.get(20) {
+        Some(0 | 1) => bytes.get(2..2 + bytes[0] as usize)
+            .is_some_and(|header| header.len() >= 20 && header.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)) == bytes[1]),
+        Some(2) => u16_at(bytes, 0).is_some_and(|len| len >= 26),
+        _ => false,
+    }
+}
+
+pub fn members(format: Format, bytes: &[u8]) -> Result<Vec<Member>> {
+    match format {
+        Format::Zip => zip_members(bytes),
+        Format::Arc => Ok(arc_entries(bytes)?.into_iter().map(|e| e.member).collect()),
+        Format::Lzh => Ok(lzh_entries(bytes)?.into_iter().map(|e| e.member).collect()),
+        Format::Zoo => Ok(zoo_entries(bytes)?.into_iter().map(|e| e.member).collect()),
+    }
+}
+
+/// Decompress the member at `path`, giving up with `LoadError::TooLarge`
+/// past `limit` bytes.
+pub fn extract(format: Format, bytes: &[u8], path: &str, limit: Option<u64>) -> Result<Vec<u8>> {
+    let entries = match format {
+        Format::Zip => return zip_extract(bytes, path, limit),
+        Format::Arc => arc_entries(bytes)?,
+        Format::Lzh => lzh_entries(bytes)?,
+        Format::Zoo => zoo_entries(bytes)?,
+    };
+    let entry = entries.into_iter()
+        .find(|e| e.member.path == path)
+        .with_context(|| format!("{path} not found in archive"))?;
+    let method = entry.member.method.unwrap_or_default();
+    if !entry.member.supported {
+        bail!("{path} is compressed with {method}, which can't be extracted");
+    }
+    let data = bytes.get(entry.offset..entry.offset + entry.packed)
+        .with_context(|| format!("{path} is truncated"))?;
+    let data = if method == "packed" { unrle(data) } else { data.to_vec() };
+    if let Some(limit) = limit.filter(|&limit| data.len() as u64 > limit) {
+        return Err(LoadError::TooLarge { size: Some(data.len() as u64), limit }.into());
+    }
+    Ok(data)
+}
+
+/// The virtual directory at `dir` (empty for the top level, otherwise
+/// ending in `/`) of the archive at `archive_url`.
+pub fn listing(members: &[Member], archive_url: &str, dir: &str) -> Vec<DirEntry> {
+    let mut entries: Vec<DirEntry> = Vec::new();
+    for member in members {
+        let Some(rest) = member.path.strip_prefix(dir).filter(|r| !r.is_empty()) else {
+            continue;
+        };
+        match rest.split_once('/') {
+            // Something in a subdirectory; list the subdirectory once
+            Some((sub, _)) => {
+                let url = format!("{archive_url}{SEPARATOR}{dir}{sub}/");
+                if !entries.iter().any(|e| e.url == url) {
+                    entries.push(DirEntry { name: sub.to_string(), url, description: String::new(), size: None, is_dir: true });
+                }
+            }
+            None if member.is_dir => {}
+            None => {
+                let description = match &member.method {
+                    Some(method) if !member.supported => format!("{method} (can't extract)"),
+                    _ => String::new(),
+                };
+                entries.push(DirEntry {
+                    name: rest.to_string(),
+                    url: format!("{archive_url}{SEPARATOR}{dir}{rest}"),
+                    description,
+                    size: Some(member.size),
+                    is_dir: false,
+                });
+            }
+        }
+    }
+    entries
+}
+
+/// DOS archivers use backslashes for directories
+fn normalize(name: &str) -> String {
+    name.replace('\\', "/").trim_start_matches('/').to_string()
+}
+
+/// LZH paths may also separate directories with 0xFF
+fn lzh_name(raw: &[u8]) -> String {
+    let raw: Vec<u8> = raw.iter().map(|&b| if b == 0xff { b'/' } else { b }).collect();
+    String::from_utf8_lossy(&raw).into_owned()
+}
+
+fn zip_members(bytes: &[u8]) -> Result<Vec<Member>> {
+    let mut zip = zip::ZipArchive::new(Cursor::new(bytes)).context("Not a readable ZIP archive")?;
+    let mut members = Vec::new();
+    for i in 0..zip.len() {
+        let file = zip.by_index_raw(i)?;
+        members.push(Member {
+            path: normalize(&file.name()?),
+            size: file.size(),
+            method: None,
+            supported: true,
+            is_dir: file.is_dir(),
+        });
+    }
+    Ok(members)
+}
+
+fn zip_extract(bytes: &[u8], path: &str, limit: Option<u64>) -> Result<Vec<u8>> {
+    let mut zip = zip::ZipArchive::new(Cursor::new(bytes)).context("Not a readable ZIP archive")?;
+    for i in 0..zip.len() {
+        if normalize(&zip.by_index_raw(i)?.name()?) != path {
+            continue;
+        }
+        let file = zip.by_index(i).with_context(|| format!("Can't extract {path}"))?;
+        // The sizes in the archive may lie, so stop inflating past the limit
+        let size = file.size();
+        let mut data = Vec::new();
+        file.take(limit.map_or(u64::MAX, |limit| limit + 1))
+            .read_to_end(&mut data)
+            .with_context(|| format!("Can't extract {path}"))?;
+        if let Some(limit) = limit.filter(|&limit| data.len() as u64 > limit) {
+            return Err(LoadError::TooLarge { size: Some(size).filter(|&size| size > limit), limit }.into());
+        }
+        return Ok(data);
+    }
+    bail!("{path} not found in archive")
+}
+
+/// A member of an ARC, LZH or ZOO archive and where its data is
+struct Entry {
+    member: Member,
+    offset: usize,
+    packed: usize,
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// A NUL-terminated name in a fixed-size field
+fn c_name(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn arc_entries(bytes: &[u8]) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    // Some archives end without the 0x1A 0x00 marker
+    while pos < bytes.len() {
+        if bytes[pos] != 0x1a {
+            bail!("Corrupt ARC archive at offset {pos}");
+        }
+        let method = bytes.get(pos + 1).copied().unwrap_or(0);
+        if method == 0 {
+            break;
+        }
+        // Method 1 is the original format, without the unpacked size
+        let header = if method == 1 { 25 } else { 29 };
+        let name = c_name(bytes.get(pos + 2..pos + 15).context("Truncated ARC header")?);
+        let packed = u32_at(bytes, pos + 15).context("Truncated ARC header")? as usize;
+        let size = if method == 1 { packed as u64 } else { u32_at(bytes, pos + 25).context("Truncated ARC header")? as u64 };
+        let method_name = match method {
+            1 | 2 => "stored",
+            3 => "packed",
+            4 => "squeezed",
+            5..=8 => "crunched",
+            9 => "squashed",
+            _ => "an unknown method",
+        };
+        entries.push(Entry {
+            member: Member {
+                path: normalize(&name),
+                size,
+                method: Some(method_name.to_string()),
+                supported: method <= 3,
+                is_dir: false,
+            },
+            offset: pos + header,
+            packed,
+        });
+        pos += header + packed;
+    }
+    Ok(entries)
+}
+
+/// ARC "packed": runs encoded as `byte 0x90 count`, with `0x90 0` for a
+/// literal 0x90.
+fn unrle(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut iter = data.iter();
+    while let Some(&b) = iter.next() {
+        if b != 0x90 {
+            out.push(b);
+            continue;
+        }
+        match iter.next() {
+            Some(0) | None => out.push(0x90),
+            Some(&count) => {
+                let last = out.last().copied().unwrap_or(0);
+                out.extend(std::iter::repeat_n(last, count as usize - 1));
+            }
+        }
+    }
+    out
+}
+
+fn lzh_entries(bytes: &[u8]) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() && bytes[pos] != 0 {
+        let method = bytes.get(pos + 2..pos + 7)
+            .map(|m| String::from_utf8_lossy(m).into_owned())
+            .context("Truncated LZH header")?;
+        if !method.starts_with("-l") || !method.ends_with('-') {
+            bail!("Corrupt LZH archive at offset {pos}");
+        }
+        let packed = u32_at(bytes, pos + 7).context("Truncated LZH header")? as usize;
+        let size = u32_at(bytes, pos + 11).context("Truncated LZH header")? as u64;
+        let level = bytes.get(pos + 20).copied().context("Truncated LZH header")?;
+
+        let (name, offset, packed) = match level {
+            0 | 1 => {
+                let header = bytes[pos] as usize + 2;
+                let len = bytes.get(pos + 21).copied().context("Truncated LZH header")? as usize;
+                let name = lzh_name(bytes.get(pos + 22..pos + 22 + len).context("Truncated LZH header")?);
+                // Level 1 counts its extended headers in the packed size
+                let (mut offset, mut packed) = (pos + header, packed);
+                if level == 1 {
+                    let mut ext = u16_at(bytes, pos + header - 2).unwrap_or(0) as usize;
+                    while ext > 0 {
+                        let next_ext = u16_at(bytes, offset + ext - 2).context("Truncated LZH header")? as usize;
+                        offset += ext;
+                        packed = packed.saturating_sub(ext);
+                        ext = next_ext;
+                    }
+                }
+                (name, offset, packed)
+            }
+            2 => {
+                let header = u16_at(bytes, pos).context("Truncated LZH header")? as usize;
+                // Extended headers: type, data, size of the next; 1 is the
+                // file name, 2 the directory
+                let (mut name, mut dir) = (String::new(), String::new());
+                let mut ext_pos = pos + 26;
+                let mut ext = u16_at(bytes, pos + 24).unwrap_or(0) as usize;
+                while ext >= 3 && ext_pos + ext <= pos + header {
+                    let field = bytes.get(ext_pos..ext_pos + ext).context("Truncated LZH header")?;
+                    match field[0] {
+                        1 => name = lzh_name(&field[1..ext - 2]),
+                        2 => dir = lzh_name(&field[1..ext - 2]),
+                        _ => {}
+                    }
+                    let next_ext = u16_at(field, ext - 2).unwrap_or(0) as usize;
+                    ext_pos += ext;
+                    ext = next_ext;
+                }
+                (format!("{dir}{name}"), pos + header, packed)
+            }
+            _ => bail!("Unsupported LZH header level {level}"),
+        };
+
+        // Sizes straight from the file; don't trust them to move on
+        if offset + packed <= pos {
+            bail!("Corrupt LZH archive at offset {pos}");
+        }
+        let is_dir = method == "-lhd-";
+        entries.push(Entry {
+            member: Member {
+                path: normalize(&name),
+                size,
+                supported: matches!(method.as_str(), "-lh0-" | "-lz4-"),
+                method: Some(method),
+                is_dir,
+            },
+            offset,
+            packed,
+        });
+        pos = offset + packed;
+    }
+    Ok(entries)
+}
+
+const ZOO_MAGIC: u32 = 0xFDC4_A7DC;
+
+fn zoo_entries(bytes: &[u8]) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut pos = u32_at(bytes, 24).context("Truncated ZOO header")? as usize;
+    // Each directory entry points at the next; the last one is empty
+    while u32_at(bytes, pos) == Some(ZOO_MAGIC) {
+        let next = u32_at(bytes, pos + 6).context("Truncated ZOO entry")? as usize;
+        if next == 0 {
+            break;
+        }
+        let method = bytes[pos + 5];
+        let offset = u32_at(bytes, pos + 10).context("Truncated ZOO entry")? as usize;
+        let size = u32_at(bytes, pos + 20).context("Truncated ZOO entry")? as u64;
+        let packed = u32_at(bytes, pos + 24).context("Truncated ZOO entry")? as usize;
+        let deleted = bytes.get(pos + 30).copied().unwrap_or(0) != 0;
+        let name = c_name(bytes.get(pos + 38..pos + 51).context("Truncated ZOO entry")?);
+        if !deleted {
+            entries.push(Entry {
+                member: Member {
+                    path: normalize(&name),
+                    size,
+                    method: Some(match method {
+                        0 => "stored",
+                        1 => "LZW",
+                        2 => "LZH",
+                        _ => "an unknown method",
+                    }.to_string()),
+                    supported: method == 0,
+                    is_dir: false,
+                },
+                offset,
+                packed,
+            });
+        }
+        if next <= pos {
+            bail!("Corrupt ZOO archive at offset {pos}");
+        }
+        pos = next;
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arc_member(method: u8, name: &str, data: &[u8], size: u32) -> Vec<u8> {
+        let mut out = vec![0x1a, method];
+        let mut field = name.as_bytes().to_vec();
+        field.resize(13, 0);
+        out.extend(field);
+        out.extend((data.len() as u32).to_le_bytes());
+        out.extend([0; 6]); // date, time, CRC
+        out.extend(size.to_le_bytes());
+        out.extend(data);
+        out
+    }
+
+    fn arc() -> Vec<u8> {
+        let mut out = arc_member(2, "READ.ME", b"Hello", 5);
+        out.extend(arc_member(3, "RUNS.TXT", b"ab\x90\x04c\x90\x00", 7));
+        out.extend(arc_member(8, "CRUNCH.TXT", b"????", 10));
+        out.extend([0x1a, 0]);
+        out
+    }
+
+    /// A level 0 LZH header and data, or level 2 with the name split into
+    /// directory and file extended headers
+    fn lzh_member(level: u8, method: &str, name: &str, data: &[u8]) -> Vec<u8> {
+        let mut fields = method.as_bytes().to_vec();
+        fields.extend((data.len() as u32).to_le_bytes());
+        fields.extend((data.len() as u32).to_le_bytes());
+        fields.extend([0; 4]); // time
+        let mut out = match level {
+            0 => {
+                fields.extend([0x20, 0, name.len() as u8]);
+                fields.extend(name.as_bytes());
+                fields.extend([0; 2]); // CRC
+                let checksum = fields.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+                let mut out = vec![fields.len() as u8, checksum];
+                out.extend(fields);
+                out
+            }
+            _ => {
+                let (dir, file) = name.rsplit_once('/').unwrap_or(("", name));
+                fields.extend([0x20, 2, 0, 0, b'U']); // reserved, level, CRC, OS
+                let mut out = vec![0, 0];
+                out.extend(fields);
+                let ext_headers = [
+                    (1u8, file.as_bytes().to_vec()),
+                    (2u8, format!("{dir}/").into_bytes()),
+                ];
+                out.extend((3 + ext_headers[0].1.len() as u16).to_le_bytes());
+                for (i, (kind, value)) in ext_headers.iter().enumerate() {
+                    out.push(*kind);
+                    out.extend(value);
+                    let next = ext_headers.get(i + 1).map_or(0, |(_, v)| 3 + v.len() as u16);
+                    out.extend(next.to_le_bytes());
+                }
+                let len = out.len() as u16;
+                out[..2].copy_from_slice(&len.to_le_bytes());
+                out
+            }
+        };
+        out.extend(data);
+        out
+    }
+
+    fn zoo(entries: &[(&str, &[u8], bool)]) -> Vec<u8> {
+        let mut out = b"ZOO 2.10 Archive.\x1a\0\0".to_vec();
+        out.extend(ZOO_MAGIC.to_le_bytes());
+        out.extend(34u32.to_le_bytes());
+        out.resize(34, 0);
+        for (name, data, deleted) in entries {
+            let at = out.len();
+            let next = at + 56 + data.len();
+            out.extend(ZOO_MAGIC.to_le_bytes());
+            out.extend([1, 0]); // type, method
+            out.extend((next as u32).to_le_bytes());
+            out.extend(((at + 56) as u32).to_le_bytes());
+            out.extend([0; 6]); // date, time, CRC
+            out.extend((data.len() as u32).to_le_bytes());
+            out.extend((data.len() as u32).to_le_bytes());
+            out.extend([2, 0, *deleted as u8, 0, 0, 0, 0, 0, 0, 0]);
+            let mut field = name.as_bytes().to_vec();
+            field.resize(13, 0);
+            out.extend(field);
+            out.resize(at + 56, 0);
+            out.extend(*data);
+        }
+        out.extend(ZOO_MAGIC.to_le_bytes());
+        out.extend([1, 0, 0, 0, 0, 0]);
+        out.resize(out.len() + 46, 0);
+        out
+    }
+
+    fn paths(format: Format, bytes: &[u8]) -> Vec<String> {
+        members(format, bytes).unwrap().into_iter().map(|m| m.path).collect()
+    }
+
+    #[test]
+    fn reads_arc_archives() {
+        let bytes = arc();
+        assert_eq!(detect(&bytes), Some(Format::Arc));
+        assert_eq!(paths(Format::Arc, &bytes), ["READ.ME", "RUNS.TXT", "CRUNCH.TXT"]);
+        assert_eq!(extract(Format::Arc, &bytes, "READ.ME", None).unwrap(), b"Hello");
+        assert_eq!(extract(Format::Arc, &bytes, "RUNS.TXT", None).unwrap(), b"abbbbc\x90");
+        let e = extract(Format::Arc, &bytes, "CRUNCH.TXT", None).err().unwrap();
+        assert_eq!(e.to_string(), "CRUNCH.TXT is compressed with crunched, which can't be extracted");
+    }
+
+    #[test]
+    fn expands_arc_runs() {
+        assert_eq!(unrle(b"x\x90\x03y"), b"xxxy");
+        assert_eq!(unrle(b"\x90\x00\x90\x02"), b"\x90\x90");
+        // A run with nothing before it repeats NULs; a trailing marker is literal
+        assert_eq!(unrle(b"\x90\x03"), b"\0\0");
+        assert_eq!(unrle(b"z\x90"), b"z\x90");
+    }
+
+    #[test]
+    fn reads_lzh_archives() {
+        let mut bytes = lzh_member(0, "-lh0-", "readme.txt", b"Hi there");
+        bytes.extend(lzh_member(2, "-lh0-", "docs/phreak.txt", b"2600"));
+        bytes.extend(lzh_member(2, "-lh5-", "docs/big.txt", b"????"));
+        bytes.push(0);
+        assert_eq!(detect(&bytes), Some(Format::Lzh));
+        assert_eq!(paths(Format::Lzh, &bytes), ["readme.txt", "docs/phreak.txt", "docs/big.txt"]);
+        assert_eq!(extract(Format::Lzh, &bytes, "readme.txt", None).unwrap(), b"Hi there");
+        assert_eq!(extract(Format::Lzh, &bytes, "docs/phreak.txt", None).unwrap(), b"2600");
+        assert!(extract(Format::Lzh, &bytes, "docs/big.txt", None).is_err());
+    }
+
+    #[test]
+    fn text_with_an_lzh_method_is_not_an_archive() {
+        let text = b"  -lh5- is the usual LHarc method, -lh0- stores files as they are\r\n";
+        assert_eq!(detect(text), None);
+        assert_eq!(crate::binary::detect(text, None), None);
+        // A real header stops counting once its checksum is off
+        let mut bytes = lzh_member(0, "-lh0-", "readme.txt", b"Hi there");
+        assert_eq!(detect(&bytes), Some(Format::Lzh));
+        bytes[1] ^= 1;
+        assert_eq!(detect(&bytes), None);
+    }
+
+    #[test]
+    fn stops_extracting_at_the_limit() {
+        use std::io::Write;
+        use zip::write::SimpleFileOptions;
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("BOMB.TXT", SimpleFileOptions::default()).unwrap();
+        zip.write_all(&[b'A'; 1 << 20]).unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+        assert!(bytes.len() < 1 << 12);
+
+        let e = extract(Format::Zip, &bytes, "BOMB.TXT", Some(1 << 16)).unwrap_err();
+        assert!(matches!(
+            crate::error::classify(&e),
+            Some(LoadError::TooLarge { size: Some(0x100000), limit: 0x10000 }),
+        ));
+        assert_eq!(extract(Format::Zip, &bytes, "BOMB.TXT", None).unwrap().len(), 1 << 20);
+
+        let arc = arc();
+        assert!(extract(Format::Arc, &arc, "READ.ME", Some(5)).is_ok());
+        assert!(extract(Format::Arc, &arc, "RUNS.TXT", Some(5)).is_err());
+    }
+
+    #[test]
+    fn reads_zoo_archives() {
+        let bytes = zoo(&[("GONE.TXT", b"old", true), ("KEEP.TXT", b"new", false)]);
+        assert_eq!(detect(&bytes), Some(Format::Zoo));
+        assert_eq!(paths(Format::Zoo, &bytes), ["KEEP.TXT"]);
+        assert_eq!(extract(Format::Zoo, &bytes, "KEEP.TXT", None).unwrap(), b"new");
+    }
+
+    #[test]
+    fn rejects_truncated_and_corrupt_archives() {
+        let arc = arc();
+        let lzh = lzh_member(2, "-lh0-", "docs/phreak.txt", b"2600");
+        let zoo = zoo(&[("KEEP.TXT", b"new", false)]);
+        for (format, bytes) in [(Format::Arc, &arc), (Format::Lzh, &lzh), (Format::Zoo, &zoo)] {
+            for len in 1..bytes.len() {
+                // Cut anywhere, it's an error or a shorter listing, never a panic
+                let _ = members(format, &bytes[..len]);
+                if let Ok(members) = members(format, &bytes[..len]) {
+                    for member in members {
+                        let _ = extract(format, &bytes[..len], &member.path, None);
+                    }
+                }
+            }
+        }
+
+        // A level 2 header that claims to be longer than the file
+        let mut long = lzh.clone();
+        long[..2].copy_from_slice(&500u16.to_le_bytes());
+        long[24..26].copy_from_slice(&400u16.to_le_bytes());
+        assert!(members(Format::Lzh, &long).is_err());
+
+        // A ZOO entry pointing back at itself
+        let mut looped = zoo.clone();
+        looped[40..44].copy_from_slice(&34u32.to_le_bytes());
+        assert!(members(Format::Zoo, &looped).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+//! Binary file detection and hex dumps
+
+use crate::archive;
+
+/// Bytes per hex dump row; halved on narrow terminals
+const DUMP_WIDTH: usize = 16;
+
+/// How much of a file to look at when guessing text vs binary
+const SNIFF_LEN: usize = 4096;
+
+#[derive(Debug, Clone)]
+pub struct Binary {
+    /// Human readable file type, e.g. "ZIP archive"
+    pub kind: String,
+    pub bytes: Vec<u8>,
+}
+
+/// File types recognised by their first bytes, besides archives
+const MAGIC: &[(&[u8], &str)] = &[
+    (b"GIF87a", "GIF image"),
+    (b"GIF89a", "GIF image"),
+    (b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (b"\xff\xd8\xff", "JPEG image"),
+    (b"%PDF", "PDF document"),
+    (b"\x1f\x8b", "gzip archive"),
+    (b"Rar!", "RAR archive"),
+    (b"\x7fELF", "ELF executable"),
+];
+
+/// Signatures that a text file could start with too ("BMUG news",
+/// "MZ-80 notes"), only believed for content that doesn't look like text
+const WEAK_MAGIC: &[(&[u8], &str)] = &[
+    (b"BM", "BMP image"),
+    (b"MZ", "DOS executable"),
+    (b"MThd", "MIDI file"),
+    (b"RIFF", "RIFF media file"),
+];
+
+/// Decide whether a fetched file is binary, and if so what kind.
+pub fn detect(bytes: &[u8], content_type: Option<&str>) -> Option<String> {
+    if let Some(format) = archive::detect(bytes) {
+        return Some(format!("{} archive", format.name()));
+    }
+    if let Some((_, kind)) = MAGIC.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        return Some(kind.to_string());
+    }
+
+    let mime = content_type.unwrap_or_default().split(';').next().unwrap_or_default().trim().to_lowercase();
+    if mime.starts_with("image/") || mime.starts_with("audio/") || mime.starts_with("video/") {
+        return Some(mime);
+    }
+    if mime.starts_with("text/") {
+        return None;
+    }
+
+    // Unknown: text files don't have NULs and have few control characters.
+    // ESC, form feed and ^Z are common in ANSI art and DOS text.
+    let sample = &bytes[..bytes.len().min(SNIFF_LEN)];
+    let control = sample.iter()
+        .filter(|&&b| b < 0x20 && !b"\t\n\r\x0c\x1a\x1b".contains(&b))
+        .count();
+    if !sample.contains(&0) && control * 10 <= sample.len() {
+        return None;
+    }
+    if let Some((_, kind)) = WEAK_MAGIC.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        return Some(kind.to_string());
+    }
+    Some(if mime.is_empty() { "binary file".to_string() } else { mime })
+}
+
+/// Bytes per hex dump row: 16, or 8 if `width` can't fit that.
+pub fn row_len(width: usize) -> usize {
+    // Offset, gaps and bars take 13 columns; each byte 3 in hex plus 1
+    if width >= 13 + DUMP_WIDTH * 4 { DUMP_WIDTH } else { DUMP_WIDTH / 2 }
+}
+
+/// Rows in a hex dump of `bytes`, `row_len` bytes to a row.
+pub fn dump_rows(bytes: &[u8], row_len: usize) -> usize {
+    bytes.len().div_ceil(row_len)
+}
+
+/// Row `row` of a hex dump of `bytes`, `offset  hex bytes  |ascii|`. Made
+/// one at a time, so a big file only costs the rows on screen.
+pub fn dump_row(bytes: &[u8], row: usize, row_len: usize) -> String {
+    let start = (row * row_len).min(bytes.len());
+    let chunk = &bytes[start..(start + row_len).min(bytes.len())];
+    let mut hex = String::new();
+    for (j, b) in chunk.iter().enumerate() {
+        if j == row_len / 2 {
+            hex.push(' ');
+        }
+        hex.push_str(&format!("{b:02x} "));
+    }
+    let pad = row_len * 3 + 1;
+    let ascii: String = chunk.iter()
+        .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+        .collect();
+    format!("{start:08x}  {hex:<pad$}|{ascii}|")
+}
+
+/// At most five columns for the directory listing: `512`, `12K`, `3.4M`
+pub fn short_size(bytes: u64) -> String {
+    const K: u64 = 1024;
+    if bytes < K {
+        bytes.to_string()
+    } else if bytes < K * K {
+        format!("{}K", bytes / K)
+    } else if bytes < 10 * K * K {
+        format!("{:.1}M", bytes as f64 / (K * K) as f64)
+    } else if bytes < K * K * K {
+        format!("{}M", bytes / (K * K))
+    } else {
+        format!("{}G", bytes / (K * K * K))
+    }
+}
+
+/// `512 B`, `12.1 KB`, `3.4 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_that_starts_like_a_signature_is_text() {
+        for text in ["BMUG Newsletter, Spring 1988\r\n", "MZ-80 tips\n", "MThd is how MIDI files start\n", "RIFF\n"] {
+            assert_eq!(detect(text.as_bytes(), Some("text/plain")), None, "{text}");
+            assert_eq!(detect(text.as_bytes(), None), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn dumps_rows_on_demand() {
+        let bytes: Vec<u8> = (0..20).chain(*b"Hi!").collect();
+        assert_eq!((row_len(80), row_len(60)), (16, 8));
+        assert_eq!((dump_rows(&bytes, 16), dump_rows(&bytes, 8), dump_rows(&[], 16)), (2, 3, 0));
+        assert_eq!(
+            dump_row(&bytes, 0, 8),
+            "00000000  00 01 02 03  04 05 06 07 |........|",
+        );
+        assert_eq!(
+            dump_row(&bytes, 1, 16),
+            "00000010  10 11 12 13 48 69 21                             |....Hi!|",
+        );
+    }
+
+    #[test]
+    fn recognises_binaries() {
+        assert_eq!(detect(b"BM\x36\x00\x0c\x00\x00\x00", None).as_deref(), Some("BMP image"));
+        assert_eq!(detect(b"MZ\x90\x00\x03\x00\x00\x00", Some("application/octet-stream")).as_deref(), Some("DOS executable"));
+        assert_eq!(detect(b"GIF89a\x01\x00", Some("text/plain")).as_deref(), Some("GIF image"));
+        assert_eq!(detect(b"\x00\x01\x02\x03", None).as_deref(), Some("binary file"));
+        assert_eq!(detect(b"\x1b[1;32mANSI art\x1b[0m\r\n\x1a", None), None);
+    }
+}
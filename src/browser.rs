@@ -1,83 +1,369 @@
 //! Browser state and navigation
 
-use crate::fetcher::Fetcher;
+use crate::archive;
+use crate::binary::{self, Binary};
+use crate::config::Config;
+use crate::error::{self, LoadError};
+use crate::fetcher::Response;
+use crate::html::Document;
+use crate::local;
 use crate::parser::{self, DirEntry};
-use anyhow::Result;
-
-const HOME_URL: &str = "http://textfiles.com/directory.html";
+use crate::source::{ReadOptions, Sources};
+use crate::wayback::{self, Wayback};
+use anyhow::{Context, Result};
 
 #[derive(Debug, Clone)]
 pub enum Content {
     Directory(Vec<DirEntry>),
     TextFile(String),
+    /// An HTML page that isn't a listing, rendered as text
+    Document(Document),
+    /// Anything that isn't text, shown as a hex dump
+    Binary(Binary),
 }
 
 #[derive(Debug, Clone)]
 pub struct Page {
     pub title: String,
     pub content: Content,
+    /// Different from the copy read last time
+    pub changed: bool,
 }
 
 pub struct Browser {
-    fetcher: Fetcher,
+    sources: Sources,
+    pub wayback: Wayback,
     pub history: Vec<String>,
     pub current_url: String,
+    pub home_url: String,
+}
+
+/// How a finished load changes the history
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Visit {
+    /// A new page; the current one goes on the history
+    Forward,
+    /// The page on top of the history
+    Back,
+    /// The current page again
+    Reload,
+}
+
+/// A loaded page, ready to become the current one
+pub struct Loaded {
+    /// Where it came from, which is a snapshot's URL after a fallback
+    pub url: String,
+    pub page: Page,
+    /// Something the UI should tell the user about the load
+    pub notice: Option<String>,
+}
+
+/// What a browser needs to load pages, for use on another thread
+#[derive(Clone)]
+pub struct Loader {
+    sources: Sources,
+    wayback: Wayback,
+}
+
+impl Loader {
+    /// Load `url` from the site or one of its mirrors, falling back to its
+    /// latest Wayback snapshot if no live copy can be had.
+    pub fn load(&self, url: &str, options: ReadOptions) -> Result<Loaded> {
+        let sources = self.sources.with_options(options);
+        // Pages on a mirror are kept under the site's own URL
+        let url = &sources.canonical(url);
+        let err = match load_page(&sources, url) {
+            Ok(page) => {
+                let notice = page.changed.then(|| "Changed since you last read it".to_string());
+                return Ok(Loaded { url: url.to_string(), page, notice });
+            }
+            Err(e) => e,
+        };
+        // A missing page or one robots.txt rules out isn't worth an old copy
+        let live = url.starts_with("http") && wayback::parse(url).is_none();
+        if !self.wayback.fallback || !live || !error::is_outage(&err) {
+            return Err(err);
+        }
+        let Ok(Some(snapshot)) = self.wayback.latest(&sources, url) else {
+            return Err(err);
+        };
+        let page = load_page(&sources, &snapshot).map_err(|_| err)?;
+        let notice = wayback::parse(&snapshot).map(|parsed| {
+            format!("Offline; showing Wayback snapshot from {}", wayback::format_timestamp(parsed.timestamp))
+        });
+        Ok(Loaded { url: snapshot, page, notice })
+    }
+
+    /// The Wayback timestamps of `url`, or of the page it's a snapshot of.
+    pub fn snapshots(&self, url: &str) -> Result<Vec<String>> {
+        self.wayback.snapshots(&self.sources, wayback::original(url))
+    }
 }
 
 impl Browser {
-    pub fn new() -> Result<Self> {
-        Ok(Self {
-            fetcher: Fetcher::new()?,
+    pub fn new(config: &Config) -> Result<Self> {
+        Ok(Self::with_sources(config, Sources::new(config)?))
+    }
+
+    /// A browser reading pages from `sources` instead of the usual ones.
+    pub fn with_sources(config: &Config, sources: Sources) -> Self {
+        Self {
+            sources,
+            wayback: Wayback::new(&config.wayback),
             history: Vec::new(),
-            current_url: HOME_URL.to_string(),
-        })
+            current_url: config.home_url.clone(),
+            home_url: config.home_url.clone(),
+        }
     }
 
-    pub fn load(&mut self, url: &str) -> Result<Page> {
-        let body = self.fetcher.fetch(url)?;
-        let start: String = body.trim_start().chars().take(15).collect();
-        let lower = start.to_lowercase();
-        let is_html = lower.starts_with("<!doctype") || lower.starts_with("<html");
+    /// A new browser sharing this one's sources, starting at the current
+    /// page with empty history.
+    pub fn fork(&self) -> Self {
+        Self {
+            sources: self.sources.clone(),
+            wayback: self.wayback.clone(),
+            history: Vec::new(),
+            current_url: self.current_url.clone(),
+            home_url: self.home_url.clone(),
+        }
+    }
 
-        if is_html {
-            let entries = if url.ends_with("directory.html") {
-                parser::parse_directory_html(&body)
-            } else {
-                parser::parse_file_listing(&body, url)
-            };
+    /// A handle on the sources for loading pages off the UI thread.
+    pub fn sources(&self) -> Sources {
+        self.sources.clone()
+    }
 
-            let title = parser::parse_page_title(&body).unwrap_or_else(|| {
-                url.trim_end_matches('/').split('/').last()
-                    .unwrap_or("TEXTFILES.COM").to_uppercase()
-            });
+    pub fn loader(&self) -> Loader {
+        Loader { sources: self.sources.clone(), wayback: self.wayback.clone() }
+    }
 
-            Ok(Page { title, content: Content::Directory(entries) })
-        } else {
-            let title = url.split('/').last().unwrap_or("file").to_string();
-            Ok(Page { title, content: Content::TextFile(body) })
+    /// Whether `url` could be read from the cache, as it would be fetched.
+    pub fn is_cached(&self, url: &str) -> bool {
+        let raw = wayback::raw_url(url);
+        let url = raw.as_deref().unwrap_or(url);
+        let url = archive::split_url(url).map_or(url, |(outer, _)| outer);
+        self.sources.is_cached(url)
+    }
+
+    /// Where going back leads.
+    pub fn previous(&self) -> Option<&str> {
+        self.history.last().map(String::as_str)
+    }
+
+    /// Make `url`, loaded for `visit`, the current page.
+    pub fn arrive(&mut self, url: String, visit: Visit) {
+        match visit {
+            Visit::Forward => self.history.push(std::mem::replace(&mut self.current_url, url)),
+            Visit::Back => {
+                self.history.pop();
+                self.current_url = url;
+            }
+            Visit::Reload => self.current_url = url,
         }
     }
 
-    pub fn navigate(&mut self, url: &str) -> Result<Page> {
-        self.history.push(self.current_url.clone());
-        self.current_url = url.to_string();
-        self.load(url)
+    pub fn can_go_back(&self) -> bool {
+        !self.history.is_empty()
     }
+}
+
+/// The directory holding `url`, or `None` at the top of a site.
+pub fn parent_url(url: &str) -> Option<String> {
+    if let Some(snapshot) = wayback::parse(url) {
+        let prefix = &url[..url.len() - snapshot.original.len()];
+        return parent_url(snapshot.original).map(|parent| format!("{prefix}{parent}"));
+    }
+    let trimmed = url.trim_end_matches('/');
+    let root = trimmed.find("://").map_or(0, |i| i + 3);
+    let cut = trimmed.rfind('/').filter(|&i| i >= root)?;
+    Some(trimmed[..=cut].to_string())
+}
+
+/// Read `url` from its source, reaching into archives for
+/// `archive!/member` URLs.
+fn fetch(sources: &Sources, url: &str) -> Result<Response> {
+    match archive::split_url(url) {
+        Some((outer, path)) if !path.is_empty() && !path.ends_with('/') => {
+            let outer = fetch(sources, outer)?;
+            let format = archive::detect(&outer.bytes).context("Not an archive").context(LoadError::Decode)?;
+            // A member over the limit is as big as a download over it
+            let bytes = archive::extract(format, &outer.bytes, path, sources.limit()).map_err(|e| match error::classify(&e) {
+                Some(_) => e,
+                None => e.context(LoadError::Decode),
+            })?;
+            Ok(Response { bytes, content_type: None, changed: outer.changed })
+        }
+        Some((outer, _)) => fetch(sources, outer),
+        None => sources.read(url),
+    }
+}
+
+/// The last part of `url`'s path, unescaped for display.
+fn name_of(url: &str) -> Option<String> {
+    url.trim_end_matches('/').rsplit('/').next().filter(|name| !name.is_empty()).map(local::decode)
+}
 
-    pub fn go_back(&mut self) -> Result<Option<Page>> {
-        if let Some(prev) = self.history.pop() {
-            self.current_url = prev.clone();
-            Ok(Some(self.load(&prev)?))
+/// Fetch `url` and turn it into a directory listing, HTML document, text
+/// or binary file.
+pub fn load_page(sources: &Sources, url: &str) -> Result<Page> {
+    // Snapshots are always fetched as captured, whatever form they came in
+    let raw = wayback::raw_url(url);
+    let url = raw.as_deref().unwrap_or(url);
+
+    if archive::split_url(url).is_none() {
+        let source = sources.get(url)?;
+        if source.metadata(url).is_ok_and(|m| m.is_dir) {
+            let title = name_of(url).unwrap_or_else(|| url.to_string());
+            return Ok(Page { title, content: Content::Directory(source.list(url)?), changed: false });
+        }
+    }
+
+    let response = fetch(sources, url)?;
+
+    if let Some(format) = archive::detect(&response.bytes) {
+        // Either a directory inside an archive, or an archive itself
+        let (archive_url, dir) = match archive::split_url(url) {
+            Some((outer, dir)) if dir.is_empty() || dir.ends_with('/') => (outer, dir),
+            _ => (url, ""),
+        };
+        if let Ok(members) = archive::members(format, &response.bytes) {
+            let name = name_of(archive_url).unwrap_or_else(|| "archive".to_string());
+            let title = format!("{name}/{dir}").trim_end_matches('/').to_string();
+            let entries = archive::listing(&members, archive_url, dir);
+            return Ok(Page { title, content: Content::Directory(entries), changed: response.changed });
+        }
+    }
+
+    if let Some(kind) = binary::detect(&response.bytes, response.content_type.as_deref()) {
+        let title = name_of(url).unwrap_or_else(|| "file".to_string());
+        let content = Content::Binary(Binary { kind, bytes: response.bytes });
+        return Ok(Page { title, content, changed: response.changed });
+    }
+
+    let mut body = String::from_utf8_lossy(&response.bytes).into_owned();
+    if looks_like_html(&body, url) {
+        if raw.is_some() {
+            body = wayback::clean_html(&body, url);
+        }
+        let content = if url.ends_with("directory.html") {
+            let entries = parser::parse_directory_html(&body, url);
+            if entries.is_empty() {
+                return Err(LoadError::Empty.into());
+            }
+            Content::Directory(entries)
         } else {
-            Ok(None)
+            let entries = parser::parse_file_listing(&body, url);
+            if parser::looks_like_listing(&body, &entries) {
+                Content::Directory(entries)
+            } else {
+                Content::Document(Document::parse(&body, url))
+            }
+        };
+
+        let title = parser::parse_page_title(&body).unwrap_or_else(|| {
+            name_of(url).unwrap_or_else(|| "TEXTFILES.COM".to_string()).to_uppercase()
+        });
+
+        Ok(Page { title, content, changed: response.changed })
+    } else {
+        let title = name_of(url).unwrap_or_else(|| "file".to_string());
+        Ok(Page { title, content: Content::TextFile(body), changed: response.changed })
+    }
+}
+
+fn looks_like_html(body: &str, url: &str) -> bool {
+    let start: String = body.trim_start().chars().take(15).collect();
+    let lower = start.to_lowercase();
+    lower.starts_with("<!doctype") || lower.starts_with("<html")
+        || (lower.starts_with('<') && [".html", ".htm"].iter().any(|ext| url.to_lowercase().ends_with(ext)))
+}
+
+/// The complete lines in the start of a download, if `load_page` will show
+/// it as plain text, so they can be read while the rest arrives.
+pub fn partial_text(url: &str, bytes: &[u8], content_type: Option<&str>) -> Option<String> {
+    if archive::split_url(url).is_some() || binary::detect(bytes, content_type).is_some() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(bytes);
+    if looks_like_html(&text, url) {
+        return None;
+    }
+    // The last line may be cut short
+    text.rfind('\n').map(|end| text[..end].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WaybackConfig;
+    use crate::parser::DirEntry;
+    use crate::source::{Metadata, MemorySource, Source};
+    use std::sync::Arc;
+
+    const PAGE: &str = "http://textfiles.com/gone.txt";
+    const SNAPSHOT: &str = "http://archive.test/web/20010516123456id_/http://textfiles.com/gone.txt";
+
+    /// An archive with a capture of `PAGE`, while textfiles.com itself is
+    /// unreachable if `down`, or just doesn't have the page.
+    struct Site {
+        archive: MemorySource,
+        down: bool,
+    }
+
+    impl Site {
+        fn check(&self, url: &str) -> Result<()> {
+            if self.down && url.starts_with("http://textfiles.com/") {
+                return Err(LoadError::Offline("textfiles.com".to_string()).into());
+            }
+            Ok(())
         }
     }
 
-    pub fn refresh(&mut self) -> Result<Page> {
-        self.load(&self.current_url.clone())
+    impl Source for Site {
+        fn metadata(&self, url: &str) -> Result<Metadata> {
+            self.check(url)?;
+            self.archive.metadata(url)
+        }
+
+        fn list(&self, url: &str) -> Result<Vec<DirEntry>> {
+            self.check(url)?;
+            self.archive.list(url)
+        }
+
+        fn read(&self, url: &str, options: ReadOptions) -> Result<Response> {
+            self.check(url)?;
+            self.archive.read(url, options)
+        }
     }
 
-    pub fn can_go_back(&self) -> bool {
-        !self.history.is_empty()
+    fn loader(down: bool) -> Loader {
+        let archive = MemorySource::new()
+            .with(
+                &format!("http://archive.test/cdx/search/cdx?url={PAGE}&fl=timestamp&filter=statuscode:200&collapse=digest"),
+                "20010516123456\n",
+                Some("text/plain"),
+            )
+            .with(SNAPSHOT, "As it was in 2001\n", Some("text/plain"));
+        let mut sources = Sources::default();
+        sources.register("http", Arc::new(Site { archive, down }));
+        let wayback = Wayback::new(&WaybackConfig { endpoint: "http://archive.test".to_string(), fallback: true });
+        Loader { sources, wayback }
+    }
+
+    #[test]
+    fn falls_back_to_a_snapshot_when_the_site_is_down() {
+        let loaded = loader(true).load(PAGE, ReadOptions::default()).unwrap();
+        assert_eq!(loaded.url, SNAPSHOT);
+        assert!(matches!(loaded.page.content, Content::TextFile(ref text) if text.contains("2001")));
+        assert_eq!(loaded.notice.as_deref(), Some("Offline; showing Wayback snapshot from 2001-05-16 12:34"));
+    }
+
+    #[test]
+    fn missing_pages_stay_missing() {
+        let err = match loader(false).load(PAGE, ReadOptions::default()) {
+            Ok(loaded) => panic!("fell back to {}", loaded.url),
+            Err(e) => e,
+        };
+        assert!(matches!(error::classify(&err), Some(LoadError::Status(404))));
     }
 }
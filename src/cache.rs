@@ -0,0 +1,152 @@
+//! Pages kept on disk between runs
+//!
+//! Each body is stored with the validators the server sent (`ETag`,
+//! `Last-Modified`), so a stale copy can be checked with a conditional
+//! request that costs a 304 instead of the whole file. Files are named
+//! after a hash of the URL; deleting the directory is always safe.
+
+use crate::config::CacheConfig;
+use crate::fetcher::Response;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub struct Cache {
+    dir: PathBuf,
+    /// How long a copy is used without asking the server
+    max_age: Duration,
+}
+
+/// A cached body and what's needed to revalidate it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Entry {
+    pub url: String,
+    #[serde(skip)]
+    pub bytes: Vec<u8>,
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// When the server last vouched for this copy, in seconds since 1970
+    pub checked: u64,
+}
+
+impl Entry {
+    pub fn into_response(self, changed: bool) -> Response {
+        Response { bytes: self.bytes, content_type: self.content_type, changed }
+    }
+
+    /// Whether a conditional request can be made for this copy.
+    pub fn has_validators(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf, max_age: Duration) -> Self {
+        Self { dir, max_age }
+    }
+
+    /// `<cache dir>/textfiles/pages`, or `None` if caching is off.
+    pub fn open(config: &CacheConfig) -> Option<Self> {
+        let dir = dirs::cache_dir()?.join("textfiles").join("pages");
+        config.enabled.then(|| Self::new(dir, Duration::from_secs(config.max_age_mins * 60)))
+    }
+
+    pub fn get(&self, url: &str) -> Option<Entry> {
+        let (meta, body) = self.paths(url);
+        let mut entry: Entry = toml::from_str(&fs::read_to_string(meta).ok()?).ok()?;
+        // Guard against the odd hash collision
+        if entry.url != url {
+            return None;
+        }
+        entry.bytes = fs::read(body).ok()?;
+        Some(entry)
+    }
+
+    /// Whether there's a copy of `url`, without reading it.
+    pub fn has(&self, url: &str) -> bool {
+        let (meta, body) = self.paths(url);
+        body.exists()
+            && fs::read_to_string(meta).ok().and_then(|meta| toml::from_str::<Entry>(&meta).ok()).is_some_and(|e| e.url == url)
+    }
+
+    /// Store `entry`, checked just now.
+    pub fn put(&self, entry: &mut Entry) -> Result<()> {
+        let (meta, body) = self.paths(&entry.url);
+        fs::create_dir_all(&self.dir)?;
+        // Without its metadata a half-written body is never read
+        let _ = fs::remove_file(&meta);
+        fs::write(body, &entry.bytes)?;
+        self.touch(entry)
+    }
+
+    /// Record that the server confirmed `entry` is current.
+    pub fn touch(&self, entry: &mut Entry) -> Result<()> {
+        entry.checked = now();
+        fs::write(self.paths(&entry.url).0, toml::to_string(entry)?)?;
+        Ok(())
+    }
+
+    /// Whether `entry` is recent enough to use without asking the server.
+    pub fn is_fresh(&self, entry: &Entry) -> bool {
+        now().saturating_sub(entry.checked) < self.max_age.as_secs()
+    }
+
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let name = format!("{:016x}", fnv1a(url.as_bytes()));
+        (self.dir.join(format!("{name}.toml")), self.dir.join(name))
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// A hash that stays the same across builds, unlike std's
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("textfiles-cache-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn keeps_bodies_with_their_validators() {
+        let dir = scratch("roundtrip");
+        let cache = Cache::new(dir.clone(), Duration::from_secs(60));
+        let url = "http://textfiles.com/hacking/phreak.txt";
+        assert!(cache.get(url).is_none());
+
+        let mut entry = Entry {
+            url: url.to_string(),
+            bytes: b"BLUE BOXES".to_vec(),
+            etag: Some("\"abc\"".to_string()),
+            ..Entry::default()
+        };
+        cache.put(&mut entry).unwrap();
+        let cached = cache.get(url).unwrap();
+        assert_eq!(cached.bytes, b"BLUE BOXES");
+        assert_eq!(cached.etag.as_deref(), Some("\"abc\""));
+        assert!(cached.last_modified.is_none());
+        assert!(cache.is_fresh(&cached));
+        assert!(cache.get("http://textfiles.com/hacking/unix.txt").is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn copies_go_stale_after_max_age() {
+        let cache = Cache::new(scratch("stale"), Duration::from_secs(60));
+        let entry = |age| Entry { checked: now() - age, ..Entry::default() };
+        assert!(cache.is_fresh(&entry(59)));
+        assert!(!cache.is_fresh(&entry(60)));
+    }
+}
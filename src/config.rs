@@ -0,0 +1,285 @@
+//! User configuration
+
+use crate::keymap::KeyOverrides;
+use crate::reflow::WrapMode;
+use crate::theme::{self, Theme};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Page loaded at startup
+    pub home_url: String,
+    /// Name of the active theme
+    pub theme: String,
+    /// Initial wrap mode for the viewer
+    pub wrap: WrapMode,
+    /// Lines moved by page up/down
+    pub page_size: usize,
+    /// Show the preview pane next to directory listings at startup
+    pub preview: bool,
+    /// Start with the one-line banner instead of the header art
+    pub compact_header: bool,
+    /// Key binding preset: vim, emacs or less
+    pub keymap: String,
+    /// Bindings added on top of the preset
+    pub keys: KeyOverrides,
+    pub network: NetworkConfig,
+    pub cache: CacheConfig,
+    pub mirrors: MirrorConfig,
+    pub wayback: WaybackConfig,
+    /// User-defined themes, keyed by name
+    pub themes: BTreeMap<String, ThemeColors>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    pub timeout_secs: u64,
+    /// Attempts per request, including the first
+    pub retries: u32,
+    /// Wait before the first retry, doubled for each one after
+    pub backoff_ms: u64,
+    /// Longest wait between attempts; a longer `Retry-After` gives up
+    pub max_backoff_secs: u64,
+    /// Requests per second to any one host; 0 for no limit
+    pub rate_limit: f64,
+    /// Requests in flight to any one host
+    pub max_connections: usize,
+    /// Skip what robots.txt disallows and wait out its crawl-delay
+    pub robots_txt: bool,
+    /// Ask before downloading files bigger than this; 0 for no limit
+    pub max_download_mb: u64,
+    pub user_agent: String,
+    /// Proxy for every request, e.g. `http://proxy:3128` or
+    /// `socks5h://proxy:1080`. Empty uses `HTTP_PROXY`, `HTTPS_PROXY` and
+    /// `ALL_PROXY`; `none` connects directly.
+    pub proxy: String,
+    /// Comma-separated hosts `proxy` doesn't apply to; empty uses `NO_PROXY`
+    pub no_proxy: String,
+    /// Extra certificate authorities to trust, as PEM or DER files
+    pub ca_certs: Vec<PathBuf>,
+    /// Try https:// for http:// URLs, falling back for hosts without it
+    pub prefer_https: bool,
+    /// Sent with every request
+    pub headers: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Keep fetched pages on disk between runs
+    pub enabled: bool,
+    /// How long a cached page is used before asking the server whether it changed
+    pub max_age_mins: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MirrorConfig {
+    /// Root of the site itself; history and bookmarks keep URLs under it
+    pub site: String,
+    /// Roots of copies of the site, tried in order when it can't be reached
+    pub urls: Vec<String>,
+    /// How long a root's last answer is trusted before it's checked again
+    pub recheck_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaybackConfig {
+    /// Wayback Machine to use, or a stand-in with the same API
+    pub endpoint: String,
+    /// Show the latest snapshot when a page can't be loaded live
+    pub fallback: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemeColors {
+    pub bright: String,
+    pub normal: String,
+    pub dim: String,
+    pub background: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alert: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            home_url: "http://textfiles.com/directory.html".to_string(),
+            theme: theme::DEFAULT_THEME.to_string(),
+            wrap: WrapMode::Hard,
+            page_size: 20,
+            preview: false,
+            compact_header: false,
+            keymap: "vim".to_string(),
+            keys: KeyOverrides::default(),
+            network: NetworkConfig::default(),
+            cache: CacheConfig::default(),
+            mirrors: MirrorConfig::default(),
+            wayback: WaybackConfig::default(),
+            themes: BTreeMap::new(),
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// `max_download_mb` in bytes, if there's a limit.
+    pub fn max_download(&self) -> Option<u64> {
+        (self.max_download_mb > 0).then_some(self.max_download_mb << 20)
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 30,
+            retries: 3,
+            backoff_ms: 500,
+            max_backoff_secs: 30,
+            rate_limit: 2.0,
+            max_connections: 2,
+            robots_txt: true,
+            max_download_mb: 10,
+            user_agent: "TextfilesBrowser/1.0".to_string(),
+            proxy: String::new(),
+            no_proxy: String::new(),
+            ca_certs: Vec::new(),
+            prefer_https: false,
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { enabled: true, max_age_mins: 60 }
+    }
+}
+
+impl Default for MirrorConfig {
+    fn default() -> Self {
+        Self { site: "http://textfiles.com/".to_string(), urls: Vec::new(), recheck_secs: 300 }
+    }
+}
+
+impl Default for WaybackConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: "https://web.archive.org".to_string(),
+            fallback: true,
+        }
+    }
+}
+
+impl Config {
+    /// `<config dir>/textfiles/config.toml`
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("textfiles").join("config.toml"))
+    }
+
+    /// Load the config file, falling back to defaults if there is none.
+    pub fn load() -> Result<Self> {
+        match Self::path() {
+            Some(path) if path.exists() => Self::read(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// The config file at `path`, with defaults for whatever it leaves out.
+    fn read(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// The effective configuration as TOML.
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).context("Failed to serialize config")
+    }
+
+    /// Built-in themes followed by user-defined ones. A user theme with
+    /// a built-in name replaces it.
+    pub fn themes(&self) -> Result<Vec<Theme>> {
+        let mut themes = Theme::builtin();
+        for (name, colors) in &self.themes {
+            let theme = Theme::from_colors(name, colors)?;
+            match themes.iter_mut().find(|t| t.name == *name) {
+                Some(existing) => *existing = theme,
+                None => themes.push(theme),
+            }
+        }
+        Ok(themes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::Keymap;
+
+    /// `text` as a config file, read back
+    fn read(name: &str, text: &str) -> Result<Config> {
+        let path = std::env::temp_dir().join(format!("textfiles-config-{name}-{}.toml", std::process::id()));
+        fs::write(&path, text).unwrap();
+        let config = Config::read(&path);
+        fs::remove_file(path).unwrap();
+        config
+    }
+
+    #[test]
+    fn fills_in_defaults() {
+        let config = read("empty", "").unwrap();
+        assert_eq!(config.home_url, "http://textfiles.com/directory.html");
+        assert_eq!((config.theme.as_str(), config.keymap.as_str(), config.page_size), ("phosphor", "vim", 20));
+        assert_eq!((config.network.retries, config.network.max_download_mb, config.network.robots_txt), (3, 10, true));
+        assert!(config.cache.enabled && config.wayback.fallback && config.mirrors.urls.is_empty());
+        // The printed config reads back the same
+        let printed = toml::from_str::<Config>(&config.to_toml().unwrap()).unwrap();
+        assert_eq!(printed.to_toml().unwrap(), config.to_toml().unwrap());
+    }
+
+    #[test]
+    fn reads_partial_files() {
+        let config = read("partial", r##"
+theme = "amber"
+wrap = "reflow"
+keymap = "emacs"
+colour = "ignored"
+
+[network]
+retries = 5
+
+[keys.viewer]
+"<C-d>" = "page_down"
+
+[themes.mine]
+bright = "#ffffff"
+normal = "gray"
+dim = "darkgray"
+background = "black"
+"##).unwrap();
+        assert_eq!((config.theme.as_str(), config.wrap), ("amber", WrapMode::Reflow));
+        assert_eq!((config.network.retries, config.network.timeout_secs), (5, 30));
+        assert_eq!(config.page_size, 20);
+        assert!(Keymap::from_config(&config).is_ok());
+        assert!(config.themes().unwrap().iter().any(|t| t.name == "mine"));
+    }
+
+    #[test]
+    fn reports_bad_files() {
+        let e = read("malformed", "page_size = \"twenty\"\n").unwrap_err();
+        assert!(e.to_string().starts_with("Failed to parse ") && e.to_string().ends_with(".toml"), "{e}");
+        assert!(format!("{e:#}").contains("page_size"), "{e:#}");
+        assert!(read("syntax", "[network\n").is_err());
+        assert!(read("wrap", "wrap = \"diagonal\"\n").is_err());
+        assert!(read("action", "[keys.global]\n\"q\" = \"fly\"\n").is_err());
+
+        let keymap = |text| Keymap::from_config(&read("keymap", text).unwrap()).map(drop);
+        let e = keymap("keymap = \"wordstar\"\n").unwrap_err();
+        assert_eq!(e.to_string(), "Unknown keymap \"wordstar\" (expected one of: vim, emacs, less)");
+        assert!(keymap("[keys.global]\n\"<Bogus>\" = \"quit\"\n").is_err());
+    }
+}
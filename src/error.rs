@@ -0,0 +1,197 @@
+//! Why a page couldn't be loaded, and what to do about it
+//!
+//! Errors stay `anyhow::Error` on their way up; a `LoadError` is attached
+//! as context where the failure is first understood, and the UI finds it
+//! again with [`classify`] to explain it.
+
+use crate::binary;
+use reqwest::StatusCode;
+use std::error::Error as _;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadError {
+    /// The host name didn't resolve
+    Dns(String),
+    Timeout,
+    /// The server answered with something other than success
+    Status(u16),
+    /// The bytes arrived but couldn't be read, e.g. a damaged archive
+    Decode,
+    /// A page that should list entries had none
+    Empty,
+    /// No connection could be made at all
+    Offline(String),
+    /// The site's robots.txt asks crawlers to stay away
+    Robots,
+    /// The file is over the download limit; its size if the server said
+    TooLarge { size: Option<u64>, limit: u64 },
+}
+
+/// Something the user can do from the error box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Suggestion {
+    Retry,
+    /// Open the copy of the page kept on disk, however old
+    Cached,
+    /// Open a Wayback Machine copy of the page
+    Snapshot,
+    /// Go to the directory containing the page
+    Up,
+    /// Load the page again, ignoring the size limit
+    Download,
+}
+
+impl Suggestion {
+    pub fn key(self) -> char {
+        match self {
+            Self::Retry => 'r',
+            Self::Cached => 'c',
+            Self::Snapshot => 'w',
+            Self::Up => 'u',
+            Self::Download => 'y',
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Retry => "Retry",
+            Self::Cached => "Cached copy",
+            Self::Snapshot => "Archived copy",
+            Self::Up => "Up a level",
+            Self::Download => "Download anyway",
+        }
+    }
+}
+
+impl LoadError {
+    /// Classify a failed request to `url`.
+    pub fn from_request(e: &reqwest::Error, url: &str) -> Self {
+        let host = url.split_once("://").map_or(url, |(_, rest)| rest).split('/').next().unwrap_or_default();
+        if e.is_timeout() {
+            return Self::Timeout;
+        }
+        // reqwest has no flag for resolver failures; hyper words them like this
+        let mut source = e.source();
+        while let Some(cause) = source {
+            let text = cause.to_string();
+            if text.contains("dns error") || text.contains("failed to lookup address") {
+                return Self::Dns(host.to_string());
+            }
+            source = cause.source();
+        }
+        if e.is_decode() || e.is_body() {
+            return Self::Decode;
+        }
+        Self::Offline(host.to_string())
+    }
+
+    /// A sentence or two on what went wrong, for people who don't speak HTTP.
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            Self::Dns(_) => "The site's name couldn't be looked up. Check the address, \
+                or your connection if other sites fail too.",
+            Self::Timeout => "The server took too long to answer. It may be busy; try again in a moment.",
+            Self::Status(404 | 410) => "The server doesn't have this page. It may have moved; \
+                the directory above or an archived copy may still have it.",
+            Self::Status(401 | 403) => "The server won't show this page.",
+            Self::Status(429) => "The server asked us to slow down. Wait a little before retrying.",
+            Self::Status(500..) => "The server had trouble answering. That usually passes; try again shortly.",
+            Self::Status(_) => "The server didn't accept the request.",
+            Self::Decode => "The file arrived but couldn't be read. It may be damaged or use an unsupported format.",
+            Self::Empty => "The page loaded but listed nothing. Its layout may have changed, \
+                or the server sent an error page instead.",
+            Self::Offline(_) => "No connection could be made. You may be offline, or the server is down.",
+            Self::Robots => "The site's robots.txt asks programs like this one not to fetch it. \
+                Turn off robots_txt in the config to fetch it anyway.",
+            Self::TooLarge { .. } => "The file is bigger than max_download_mb in the config. \
+                It may take a while to download.",
+        }
+    }
+
+    /// What's worth trying, best first.
+    pub fn suggestions(&self) -> &'static [Suggestion] {
+        use Suggestion::*;
+        match self {
+            Self::Dns(_) | Self::Offline(_) | Self::Timeout => &[Retry, Cached, Snapshot],
+            Self::Status(404 | 410) => &[Snapshot, Up],
+            Self::Status(429 | 500..) => &[Retry, Cached, Snapshot],
+            Self::Status(_) => &[Up, Snapshot],
+            Self::Decode => &[Retry, Up],
+            Self::Empty => &[Snapshot, Retry, Up],
+            Self::Robots => &[Snapshot, Up],
+            Self::TooLarge { .. } => &[Download, Up],
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dns(host) => write!(f, "Can't find host {host}"),
+            Self::Timeout => write!(f, "Timed out"),
+            Self::Status(code) => match StatusCode::from_u16(*code) {
+                Ok(status) => write!(f, "HTTP {status}"),
+                Err(_) => write!(f, "HTTP {code}"),
+            },
+            Self::Decode => write!(f, "Can't read the file"),
+            Self::Empty => write!(f, "Nothing on the page"),
+            Self::Offline(host) => write!(f, "Can't connect to {host}"),
+            Self::Robots => write!(f, "Blocked by robots.txt"),
+            Self::TooLarge { size: Some(size), .. } => write!(f, "File is {}", binary::format_size(*size)),
+            Self::TooLarge { size: None, limit } => write!(f, "File is over {}", binary::format_size(*limit)),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// The `LoadError` behind `e`, if it's one we understand.
+pub fn classify(e: &anyhow::Error) -> Option<&LoadError> {
+    e.downcast_ref::<LoadError>()
+}
+
+/// Whether `e` means the server couldn't be reached or is failing, rather
+/// than that the page is missing or off limits.
+pub fn is_outage(e: &anyhow::Error) -> bool {
+    matches!(
+        classify(e),
+        Some(LoadError::Dns(_) | LoadError::Offline(_) | LoadError::Timeout | LoadError::Status(500..))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Context};
+
+    #[test]
+    fn finds_the_load_error_behind_context() {
+        let e = anyhow::Error::from(LoadError::Timeout);
+        assert_eq!(classify(&e), Some(&LoadError::Timeout));
+        let e: anyhow::Error = Err::<(), _>(anyhow!("connection reset"))
+            .context(LoadError::Offline("textfiles.com".into()))
+            .context("Loading http://textfiles.com/")
+            .unwrap_err();
+        assert_eq!(classify(&e), Some(&LoadError::Offline("textfiles.com".into())));
+        assert_eq!(classify(&anyhow!("unclassified")), None);
+    }
+
+    #[test]
+    fn tells_outages_from_missing_pages() {
+        for (e, outage) in [
+            (LoadError::Dns("textfiles.com".into()), true),
+            (LoadError::Offline("textfiles.com".into()), true),
+            (LoadError::Timeout, true),
+            (LoadError::Status(503), true),
+            (LoadError::Status(404), false),
+            (LoadError::Status(429), false),
+            (LoadError::Robots, false),
+            (LoadError::TooLarge { size: None, limit: 1 }, false),
+        ] {
+            let label = e.to_string();
+            assert_eq!(is_outage(&e.into()), outage, "{label}");
+        }
+        assert!(!is_outage(&anyhow!("unclassified")));
+    }
+}
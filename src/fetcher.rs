@@ -1,42 +1,633 @@
 //! HTTP fetcher for textfiles.com
+//!
+//! Failed requests are retried with exponential backoff, except where
+//! another try can't help (most 4xx responses, unknown hosts). Bodies are
+//! kept in the disk cache and revalidated with conditional requests once
+//! they're older than its max age, or when the user reloads. Bodies are
+//! read a chunk at a time, reporting progress and stopping at the size
+//! limit. With `prefer_https`, plain HTTP URLs are tried over HTTPS first
+//! and each host remembered for whether that worked.
 
+use crate::cache::{Cache, Entry};
+use crate::config::NetworkConfig;
+use crate::error::{self, LoadError};
+use crate::governor::Governor;
+use crate::parser::{self, DirEntry};
+use crate::progress::{self, Progress};
+use crate::robots::Robots;
+use crate::source::{Metadata, ReadOptions, Source};
 use anyhow::{Context, Result};
 use reqwest::blocking::Client;
-use std::{thread, time::Duration};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, NoProxy, Proxy};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::hash::{BuildHasher, RandomState};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
+/// Bytes read before the start of a body is reported, and all a head-only
+/// read gets
+pub const HEAD_LEN: usize = 16 * 1024;
+/// Least time between download progress reports
+const REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A successful response body and how the server described it
+pub struct Response {
+    pub bytes: Vec<u8>,
+    pub content_type: Option<String>,
+    /// Different from the cached copy read before
+    pub changed: bool,
+}
+
+#[derive(Clone)]
 pub struct Fetcher {
     client: Client,
+    retry: RetryPolicy,
+    governor: Arc<Governor>,
+    /// Whether robots.txt is checked before each request
+    robots_txt: bool,
+    user_agent: String,
+    cache: Option<Arc<Cache>>,
+    /// Largest body read without `ReadOptions::any_size`
+    max_download: Option<u64>,
+    prefer_https: bool,
+    /// Hosts tried over HTTPS, and whether it worked
+    https_hosts: Arc<Mutex<HashMap<String, bool>>>,
+}
+
+/// What a possibly conditional request got back
+enum Reply {
+    Body(Entry),
+    /// The cached copy is still current
+    NotModified,
+}
+
+/// How much of a body to read
+#[derive(Clone, Copy)]
+enum Extent {
+    /// All of it, giving up past the size limit if there is one
+    Whole(Option<u64>),
+    /// Only the first `HEAD_LEN` bytes
+    Head,
+}
+
+/// When and how long to wait before trying a failed request again
+#[derive(Debug, Clone)]
+struct RetryPolicy {
+    attempts: u32,
+    base: Duration,
+    max: Duration,
+}
+
+/// A server's `Retry-After`, kept under the error it came with
+#[derive(Debug)]
+struct RetryAfter(Duration);
+
+impl fmt::Display for RetryAfter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Server asked to retry after {}s", self.0.as_secs())
+    }
+}
+
+impl std::error::Error for RetryAfter {}
+
+impl RetryPolicy {
+    /// How long to wait after failed attempt number `attempt` (from 1), or
+    /// `None` if it isn't worth another.
+    fn wait(&self, attempt: u32, e: &anyhow::Error) -> Option<Duration> {
+        if attempt >= self.attempts {
+            return None;
+        }
+        // Client errors and missing hosts won't fix themselves
+        match error::classify(e)? {
+            LoadError::Status(408 | 429 | 500..) | LoadError::Timeout | LoadError::Offline(_) | LoadError::Decode => {}
+            LoadError::Status(_) | LoadError::Dns(_) | LoadError::Empty | LoadError::Robots
+            | LoadError::TooLarge { .. } => return None,
+        }
+        if let Some(RetryAfter(wait)) = e.downcast_ref() {
+            return (*wait <= self.max).then_some(*wait);
+        }
+        let backoff = self.base.saturating_mul(1 << (attempt - 1).min(16)).min(self.max);
+        Some(jitter(backoff))
+    }
+}
+
+/// Somewhere between half and all of `wait`, so clients that failed
+/// together don't all come back together.
+fn jitter(wait: Duration) -> Duration {
+    let random = RandomState::new().hash_one(Instant::now());
+    wait / 2 + wait.mul_f64((random % 1000) as f64 / 2000.0)
 }
 
 impl Fetcher {
-    pub fn new() -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .user_agent("TextfilesBrowser/1.0")
-            .build()
-            .context("Failed to create HTTP client")?;
-        Ok(Self { client })
-    }
-
-    pub fn fetch(&self, url: &str) -> Result<String> {
-        let mut last_err = None;
-        for _ in 0..3 {
-            match self.try_fetch(url) {
-                Ok(body) => return Ok(body),
-                Err(e) => {
-                    last_err = Some(e);
-                    thread::sleep(Duration::from_secs(1));
+    pub fn new(config: &NetworkConfig, cache: Option<Cache>) -> Result<Self> {
+        let client = client(config)?;
+        let retry = RetryPolicy {
+            attempts: config.retries.max(1),
+            base: Duration::from_millis(config.backoff_ms),
+            max: Duration::from_secs(config.max_backoff_secs),
+        };
+        Ok(Self {
+            client,
+            retry,
+            governor: Arc::new(Governor::new(config.rate_limit, config.max_connections)),
+            robots_txt: config.robots_txt,
+            user_agent: config.user_agent.clone(),
+            cache: cache.map(Arc::new),
+            max_download: config.max_download(),
+            prefer_https: config.prefer_https,
+            https_hosts: Arc::default(),
+        })
+    }
+
+    pub fn fetch(&self, url: &str) -> Result<Response> {
+        self.fetch_with(url, ReadOptions::default())
+    }
+
+    /// `url`, from the cache while the copy there is fresh, unless
+    /// `options` asks the server regardless or takes any cached copy.
+    fn fetch_with(&self, url: &str, options: ReadOptions) -> Result<Response> {
+        let cached = self.cache.as_ref().and_then(|cache| cache.get(url));
+        if options.cached {
+            return cached.map(|entry| entry.into_response(false)).with_context(|| format!("No cached copy of {url}"));
+        }
+        if let (Some(cache), Some(entry)) = (&self.cache, &cached)
+            && !options.revalidate
+            && cache.is_fresh(entry)
+        {
+            return Ok(entry.clone().into_response(false));
+        }
+        if self.robots_txt && !self.robots(url).allows(path_of(url)) {
+            return Err(LoadError::Robots.into());
+        }
+        let extent = match options {
+            ReadOptions { head: true, .. } => Extent::Head,
+            ReadOptions { any_size: true, .. } => Extent::Whole(None),
+            _ => Extent::Whole(self.max_download),
+        };
+        let reply = self.fetch_retrying(url, cached.as_ref(), extent)?;
+
+        // The cache is only an optimisation, so failing to write it isn't an error
+        match (reply, cached) {
+            (Reply::NotModified, Some(mut entry)) => {
+                if let Some(cache) = &self.cache {
+                    let _ = cache.touch(&mut entry);
+                }
+                Ok(entry.into_response(false))
+            }
+            (Reply::NotModified, None) => Err(LoadError::Status(304).into()),
+            // Only part of the file; the cached copy, if any, is no less current
+            (Reply::Body(entry), _) if options.head => Ok(entry.into_response(false)),
+            (Reply::Body(mut entry), cached) => {
+                let changed = cached.is_some_and(|old| old.bytes != entry.bytes);
+                if let Some(cache) = &self.cache {
+                    let _ = cache.put(&mut entry);
                 }
+                Ok(entry.into_response(changed))
             }
         }
-        Err(last_err.unwrap())
     }
 
-    fn try_fetch(&self, url: &str) -> Result<String> {
-        let resp = self.client.get(url).send().context("Request failed")?;
+    fn fetch_retrying(&self, url: &str, cached: Option<&Entry>, extent: Extent) -> Result<Reply> {
+        let mut attempt = 1;
+        loop {
+            let e = match self.try_fetch(url, cached, extent) {
+                Ok(reply) => return Ok(reply),
+                Err(e) => e,
+            };
+            let Some(wait) = self.retry.wait(attempt, &e) else {
+                return Err(e);
+            };
+            progress::report(Progress::Retrying { attempt, of: self.retry.attempts, wait, reason: e.to_string() });
+            thread::sleep(wait);
+            attempt += 1;
+        }
+    }
+
+    /// The robots.txt rules for `url`'s host, fetched the first time.
+    /// Sites without one allow everything; so do unreachable ones until
+    /// they can be asked.
+    fn robots(&self, url: &str) -> Arc<Robots> {
+        let host = host_of(url);
+        if let Some(robots) = self.governor.robots(host) {
+            return robots;
+        }
+        let scheme = url.split_once("://").map_or("http", |(scheme, _)| scheme);
+        // Its progress isn't the page's
+        let fetched = progress::muted(|| self.try_fetch(&format!("{scheme}://{host}/robots.txt"), None, Extent::Whole(self.max_download)));
+        let robots = match fetched {
+            Ok(Reply::Body(entry)) => Robots::parse(&String::from_utf8_lossy(&entry.bytes), &self.user_agent),
+            Ok(Reply::NotModified) => Robots::default(),
+            Err(e) if matches!(error::classify(&e), Some(LoadError::Status(_))) => Robots::default(),
+            Err(_) => return Arc::default(),
+        };
+        self.governor.set_robots(host, robots)
+    }
+
+    /// One request for `url`, over HTTPS first if that's preferred and
+    /// hasn't failed for the host before. Either way the reply is filed
+    /// under `url`, so the cache finds it again.
+    fn try_fetch(&self, url: &str, cached: Option<&Entry>, extent: Extent) -> Result<Reply> {
+        let Some(secure) = self.secure_url(url) else {
+            return self.request(url, cached, extent);
+        };
+        let reply = self.request(&secure, cached, extent).map(|reply| match reply {
+            Reply::Body(entry) => Reply::Body(Entry { url: url.to_string(), ..entry }),
+            Reply::NotModified => Reply::NotModified,
+        });
+        // Not getting through at all, as opposed to the server saying no,
+        // means no HTTPS here
+        let works = !matches!(reply.as_ref().map_err(error::classify), Err(Some(LoadError::Offline(_) | LoadError::Timeout)));
+        self.https_hosts.lock().unwrap_or_else(|e| e.into_inner()).insert(host_of(url).to_string(), works);
+        if works { reply } else { self.request(url, cached, extent) }
+    }
+
+    /// `url` over HTTPS, if it's plain HTTP and HTTPS is worth a try.
+    fn secure_url(&self, url: &str) -> Option<String> {
+        let rest = url.strip_prefix("http://").filter(|_| self.prefer_https)?;
+        let hosts = self.https_hosts.lock().unwrap_or_else(|e| e.into_inner());
+        (hosts.get(host_of(url)) != Some(&false)).then(|| format!("https://{rest}"))
+    }
+
+    /// One request for `url`, made conditional on `cached` if it has
+    /// validators, reading as much of the body as `extent` says.
+    fn request(&self, url: &str, cached: Option<&Entry>, extent: Extent) -> Result<Reply> {
+        use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+        let _permit = self.governor.acquire(host_of(url));
+        let failed = |e: reqwest::Error| {
+            let kind = LoadError::from_request(&e, url);
+            anyhow::Error::new(e).context(kind)
+        };
+        let mut request = self.client.get(url);
+        if let Some(entry) = cached {
+            if let Some(etag) = &entry.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(date) = &entry.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, date);
+            }
+        }
+        let resp = request.send().map_err(failed)?;
+        if resp.status() == reqwest::StatusCode::NOT_MODIFIED && cached.is_some_and(Entry::has_validators) {
+            return Ok(Reply::NotModified);
+        }
         if !resp.status().is_success() {
-            anyhow::bail!("HTTP {}", resp.status());
+            let status = LoadError::Status(resp.status().as_u16());
+            return Err(match retry_after(&resp) {
+                Some(wait) => anyhow::Error::new(RetryAfter(wait)).context(status),
+                None => status.into(),
+            });
+        }
+        let header = |name| resp.headers().get(name).and_then(|v| v.to_str().ok()).map(String::from);
+        let content_type = header(reqwest::header::CONTENT_TYPE);
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let total = resp.content_length();
+        if let (Extent::Whole(Some(limit)), Some(size)) = (extent, total)
+            && size > limit
+        {
+            return Err(LoadError::TooLarge { size: Some(size), limit }.into());
+        }
+        let bytes = read_body(resp, url, extent, content_type.as_deref())?;
+        Ok(Reply::Body(Entry { url: url.to_string(), bytes, content_type, etag, last_modified, checked: 0 }))
+    }
+}
+
+/// An HTTP client with the configured timeout, proxy, certificates and
+/// headers.
+fn client(config: &NetworkConfig) -> Result<Client> {
+    let mut builder = Client::builder()
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .user_agent(&config.user_agent);
+
+    // reqwest reads the proxy variables itself unless told otherwise
+    match config.proxy.trim() {
+        "" => {}
+        "none" => builder = builder.no_proxy(),
+        url => {
+            let no_proxy = match config.no_proxy.trim() {
+                "" => NoProxy::from_env(),
+                hosts => NoProxy::from_string(hosts),
+            };
+            let proxy = Proxy::all(url).with_context(|| format!("Invalid proxy {url}"))?;
+            builder = builder.proxy(proxy.no_proxy(no_proxy));
+        }
+    }
+
+    for path in &config.ca_certs {
+        let bytes = fs::read(path).with_context(|| format!("Failed to read certificate {}", path.display()))?;
+        let certs = match Certificate::from_pem_bundle(&bytes) {
+            Ok(certs) if !certs.is_empty() => certs,
+            _ => vec![Certificate::from_der(&bytes).with_context(|| format!("No certificate in {}", path.display()))?],
+        };
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
         }
-        resp.text().context("Failed to read response")
+    }
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in &config.headers {
+        let name = HeaderName::from_bytes(name.as_bytes()).with_context(|| format!("Invalid header name {name:?}"))?;
+        let mut value = HeaderValue::from_str(value).with_context(|| format!("Invalid value for header {name}"))?;
+        // Likely a token; keep it out of debug output
+        value.set_sensitive(true);
+        headers.insert(name, value);
+    }
+
+    builder.default_headers(headers).build().context("Failed to create HTTP client")
+}
+
+/// Read the body of `resp` a chunk at a time, reporting progress and
+/// stopping past the limit or at the end of the head.
+fn read_body(mut resp: reqwest::blocking::Response, url: &str, extent: Extent, content_type: Option<&str>) -> Result<Vec<u8>> {
+    let total = resp.content_length();
+    let limit = match extent {
+        Extent::Whole(limit) => limit,
+        Extent::Head => Some(HEAD_LEN as u64),
+    };
+    let mut bytes = Vec::with_capacity(total.unwrap_or_default().min(limit.unwrap_or(u64::MAX)) as usize);
+    let mut chunk = vec![0; 64 * 1024];
+    let mut reported = Instant::now();
+    loop {
+        let n = match resp.read(&mut chunk) {
+            Ok(0) => return Ok(bytes),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(body_error(e, url)),
+        };
+        let before = bytes.len();
+        bytes.extend_from_slice(&chunk[..n]);
+        let received = bytes.len() as u64;
+        if let Some(limit) = limit
+            && received > limit
+        {
+            if let Extent::Head = extent {
+                bytes.truncate(HEAD_LEN);
+                return Ok(bytes);
+            }
+            return Err(LoadError::TooLarge { size: total, limit }.into());
+        }
+        if before < HEAD_LEN && bytes.len() >= HEAD_LEN && total != Some(received) {
+            progress::report(Progress::Head { bytes: bytes.clone(), content_type: content_type.map(String::from) });
+        }
+        if reported.elapsed() >= REPORT_INTERVAL {
+            progress::report(Progress::Downloading { received, total });
+            reported = Instant::now();
+        }
+    }
+}
+
+/// Classify an error part way through a body, like one before it.
+fn body_error(e: io::Error, url: &str) -> anyhow::Error {
+    let kind = match e.get_ref().and_then(|inner| inner.downcast_ref::<reqwest::Error>()) {
+        Some(inner) => LoadError::from_request(inner, url),
+        None if e.kind() == io::ErrorKind::TimedOut => LoadError::Timeout,
+        None => LoadError::Decode,
+    };
+    anyhow::Error::new(e).context(kind)
+}
+
+/// `host[:port]` of `url`
+fn host_of(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split(['/', '?', '#']).next().unwrap_or_default()
+}
+
+/// Path and query of `url`, which is what robots.txt rules match
+fn path_of(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let path = rest.find(['/', '?']).map_or("", |i| &rest[i..]);
+    path.split('#').next().unwrap_or_default()
+}
+
+/// `Retry-After` as either seconds or an HTTP date.
+fn retry_after(resp: &reqwest::blocking::Response) -> Option<Duration> {
+    let value = resp.headers().get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = parse_http_date(value)?;
+    Some(at.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// `Sun, 06 Nov 1994 08:49:37 GMT`, the one date format servers must send.
+fn parse_http_date(s: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let [_, day, month, year, time, "GMT"] = s.split_whitespace().collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let day: i64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|m| *m == month)? as i64 + 1;
+    let year: i64 = year.parse().ok()?;
+    let mut hms = time.split(':').map(|n| n.parse::<i64>().ok());
+    let (h, m, sec) = (hms.next()??, hms.next()??, hms.next()??);
+
+    // Days since 1970-01-01 in the proleptic Gregorian calendar
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    let secs = u64::try_from(days * 86400 + h * 3600 + m * 60 + sec).ok()?;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Directories are HTML index pages, so only `load_page` can tell what a
+/// URL is once it has the body.
+impl Source for Fetcher {
+    fn metadata(&self, _url: &str) -> Result<Metadata> {
+        Ok(Metadata::default())
+    }
+
+    fn list(&self, url: &str) -> Result<Vec<DirEntry>> {
+        let response = self.fetch(url)?;
+        Ok(parser::parse_file_listing(&String::from_utf8_lossy(&response.bytes), url))
+    }
+
+    fn read(&self, url: &str, options: ReadOptions) -> Result<Response> {
+        self.fetch_with(url, options)
+    }
+
+    fn is_cached(&self, url: &str) -> bool {
+        self.cache.as_ref().is_some_and(|cache| cache.has(url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy { attempts: 4, base: Duration::from_millis(400), max: Duration::from_secs(10) }
+    }
+
+    #[test]
+    fn retries_only_what_may_recover() {
+        let policy = policy();
+        for (e, retried) in [
+            (LoadError::Status(404), false),
+            (LoadError::Status(403), false),
+            (LoadError::Dns("textfiles.com".into()), false),
+            (LoadError::Status(503), true),
+            (LoadError::Status(429), true),
+            (LoadError::Timeout, true),
+            (LoadError::Offline("textfiles.com".into()), true),
+        ] {
+            let label = e.to_string();
+            assert_eq!(policy.wait(1, &e.into()).is_some(), retried, "{label}");
+        }
+        assert_eq!(policy.wait(4, &LoadError::Timeout.into()), None);
+        assert_eq!(policy.wait(1, &anyhow::anyhow!("unclassified")), None);
+    }
+
+    #[test]
+    fn backs_off_exponentially_with_jitter() {
+        let policy = policy();
+        for (attempt, full) in [(1, 400), (2, 800), (3, 1600)] {
+            let wait = policy.wait(attempt, &LoadError::Status(502).into()).unwrap();
+            let full = Duration::from_millis(full);
+            assert!(wait >= full / 2 && wait <= full, "attempt {attempt}: {wait:?}");
+        }
+        let long = RetryPolicy { attempts: 20, ..policy };
+        assert!(long.wait(12, &LoadError::Timeout.into()).unwrap() <= long.max);
+    }
+
+    #[test]
+    fn honours_retry_after() {
+        let policy = policy();
+        let asked = |secs| anyhow::Error::new(RetryAfter(Duration::from_secs(secs))).context(LoadError::Status(503));
+        assert_eq!(policy.wait(1, &asked(7)), Some(Duration::from_secs(7)));
+        // Longer than we're willing to wait
+        assert_eq!(policy.wait(1, &asked(120)), None);
+    }
+
+    #[test]
+    fn checks_client_settings() {
+        let build = |change: fn(&mut NetworkConfig)| {
+            let mut config = NetworkConfig::default();
+            change(&mut config);
+            Fetcher::new(&config, None)
+        };
+        assert!(build(|c| c.proxy = "socks5h://proxy.example:1080".into()).is_ok());
+        assert!(build(|c| c.proxy = "none".into()).is_ok());
+        assert!(build(|c| drop(c.headers.insert("X-Token".into(), "abc".into()))).is_ok());
+        assert!(build(|c| drop(c.headers.insert("Bad Name".into(), "abc".into()))).is_err());
+        assert!(build(|c| c.ca_certs.push("/nonexistent/ca.pem".into())).is_err());
+    }
+
+    #[test]
+    fn upgrades_to_https_until_it_fails() {
+        let fetcher = Fetcher::new(&NetworkConfig { prefer_https: true, ..NetworkConfig::default() }, None).unwrap();
+        assert_eq!(fetcher.secure_url("http://textfiles.com/a.txt").as_deref(), Some("https://textfiles.com/a.txt"));
+        assert_eq!(fetcher.secure_url("https://textfiles.com/a.txt"), None);
+        fetcher.https_hosts.lock().unwrap().insert("textfiles.com".into(), false);
+        assert_eq!(fetcher.secure_url("http://textfiles.com/a.txt"), None);
+    }
+
+    /// An HTTPS server on localhost answering every request for a file with
+    /// `body` and its ETag, or 304 if asked with the current one. Requests'
+    /// `If-None-Match` headers are logged.
+    fn https_server(body: Arc<Mutex<&'static str>>, log: Arc<Mutex<Vec<Option<String>>>>) -> u16 {
+        use std::io::{BufRead, BufReader, Write};
+        let identity = native_tls::Identity::from_pkcs8(
+            include_bytes!("fixtures/localhost.pem"),
+            include_bytes!("fixtures/localhost-key.pem"),
+        )
+        .unwrap();
+        let acceptor = native_tls::TlsAcceptor::new(identity).unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = acceptor.accept(stream.unwrap()) else {
+                    continue;
+                };
+                let mut etag = None;
+                for line in BufReader::new(&mut stream).lines() {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(": ")
+                        && name.eq_ignore_ascii_case("if-none-match")
+                    {
+                        etag = Some(value.to_string());
+                    }
+                }
+                let body = *body.lock().unwrap();
+                let current = format!("\"{body}\"");
+                let reply = if etag.as_ref() == Some(&current) {
+                    "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string()
+                } else {
+                    format!("HTTP/1.1 200 OK\r\nETag: {current}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len())
+                };
+                log.lock().unwrap().push(etag);
+                let _ = stream.write_all(reply.as_bytes());
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn caches_upgraded_requests_under_the_url_asked_for() {
+        let body = Arc::new(Mutex::new("one"));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let port = https_server(body.clone(), log.clone());
+        let config = NetworkConfig {
+            prefer_https: true,
+            robots_txt: false,
+            rate_limit: 0.0,
+            ca_certs: vec![[env!("CARGO_MANIFEST_DIR"), "src", "fixtures", "localhost.pem"].iter().collect()],
+            ..NetworkConfig::default()
+        };
+        let dir = std::env::temp_dir().join(format!("textfiles-fetcher-https-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        // Always stale, so every read asks the server
+        let fetcher = Fetcher::new(&config, Some(Cache::new(dir.clone(), Duration::ZERO))).unwrap();
+        let url = format!("http://127.0.0.1:{port}/a.txt");
+
+        assert_eq!(fetcher.fetch(&url).unwrap().bytes, b"one");
+        assert_eq!(fetcher.cache.as_ref().unwrap().get(&url).unwrap().url, url);
+        let again = fetcher.fetch(&url).unwrap();
+        assert_eq!((again.bytes.as_slice(), again.changed), (&b"one"[..], false));
+        *body.lock().unwrap() = "two";
+        let changed = fetcher.fetch(&url).unwrap();
+        assert_eq!((changed.bytes.as_slice(), changed.changed), (&b"two"[..], true));
+        assert_eq!(*log.lock().unwrap(), [None, Some("\"one\"".to_string()), Some("\"one\"".to_string())]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reads_stale_copies_without_asking() {
+        let dir = std::env::temp_dir().join(format!("textfiles-fetcher-stale-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let cache = Cache::new(dir.clone(), Duration::ZERO);
+        // Nothing listens on port 1, so only the cache can answer
+        let url = "http://127.0.0.1:1/a.txt";
+        cache.put(&mut Entry { url: url.to_string(), bytes: b"old".to_vec(), ..Entry::default() }).unwrap();
+        let fetcher = Fetcher::new(&NetworkConfig::default(), Some(cache)).unwrap();
+        assert!(fetcher.is_cached(url));
+        assert!(!fetcher.is_cached("http://127.0.0.1:1/b.txt"));
+
+        let cached = ReadOptions { cached: true, ..ReadOptions::default() };
+        assert_eq!(fetcher.read(url, cached).unwrap().bytes, b"old");
+        let missing = fetcher.read("http://127.0.0.1:1/b.txt", cached).err().unwrap();
+        assert_eq!(missing.to_string(), "No cached copy of http://127.0.0.1:1/b.txt");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn parses_http_dates() {
+        let at = parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+        assert_eq!(at.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs(), 784111777);
+        let leap = parse_http_date("Thu, 29 Feb 2024 00:00:00 GMT").unwrap();
+        assert_eq!(leap.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs(), 1709164800);
+        assert!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT").is_none());
     }
 }
@@ -0,0 +1,125 @@
+//! Keeps requests to each host polite
+//!
+//! Every HTTP request, interactive or bulk, waits here for a slot: at most
+//! `max_connections` at once per host, started no closer together than the
+//! rate limit or the site's crawl-delay allows. One `Governor` is shared by
+//! all clones of a `Fetcher`.
+
+use crate::robots::Robots;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+pub struct Governor {
+    /// Shortest gap between request starts to one host
+    interval: Duration,
+    max_connections: usize,
+    hosts: Mutex<HashMap<String, Host>>,
+    freed: Condvar,
+}
+
+#[derive(Default)]
+struct Host {
+    active: usize,
+    /// When the next request may start
+    next: Option<Instant>,
+    robots: Option<Arc<Robots>>,
+}
+
+/// A request slot, given back when dropped
+pub struct Permit<'a> {
+    governor: &'a Governor,
+    host: String,
+}
+
+impl Governor {
+    /// `rate` is in requests per second; zero means no limit.
+    pub fn new(rate: f64, max_connections: usize) -> Self {
+        let interval = if rate > 0.0 { Duration::from_secs_f64(1.0 / rate) } else { Duration::ZERO };
+        Self { interval, max_connections: max_connections.max(1), hosts: Mutex::default(), freed: Condvar::new() }
+    }
+
+    /// Wait for a turn to send a request to `host`.
+    pub fn acquire(&self, host: &str) -> Permit<'_> {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            let now = Instant::now();
+            let state = hosts.entry(host.to_string()).or_default();
+            let delay = state.robots.as_ref().and_then(|r| r.crawl_delay).unwrap_or_default();
+            match state.next {
+                _ if state.active >= self.max_connections => {
+                    hosts = self.freed.wait(hosts).unwrap_or_else(|e| e.into_inner());
+                }
+                Some(next) if next > now => {
+                    // Another thread may take the slot meanwhile, so check again after
+                    hosts = self.freed.wait_timeout(hosts, next - now).unwrap_or_else(|e| e.into_inner()).0;
+                }
+                _ => {
+                    state.active += 1;
+                    state.next = Some(now + self.interval.max(delay));
+                    return Permit { governor: self, host: host.to_string() };
+                }
+            }
+        }
+    }
+
+    /// The robots.txt rules for `host`, if they've been fetched.
+    pub fn robots(&self, host: &str) -> Option<Arc<Robots>> {
+        self.hosts.lock().unwrap_or_else(|e| e.into_inner()).get(host)?.robots.clone()
+    }
+
+    pub fn set_robots(&self, host: &str, robots: Robots) -> Arc<Robots> {
+        let robots = Arc::new(robots);
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        hosts.entry(host.to_string()).or_default().robots = Some(robots.clone());
+        robots
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut hosts = self.governor.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(state) = hosts.get_mut(&self.host) {
+            state.active -= 1;
+        }
+        self.governor.freed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn spaces_out_requests_per_host() {
+        let governor = Governor::new(20.0, 4);
+        let start = Instant::now();
+        for _ in 0..3 {
+            drop(governor.acquire("textfiles.com"));
+        }
+        // Three starts need two gaps of 50ms
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        // Other hosts have their own schedule
+        let other = Instant::now();
+        drop(governor.acquire("example.com"));
+        assert!(other.elapsed() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn caps_concurrent_requests() {
+        let governor = Arc::new(Governor::new(0.0, 1));
+        let held = governor.acquire("textfiles.com");
+        let waiter = {
+            let governor = governor.clone();
+            thread::spawn(move || {
+                let start = Instant::now();
+                drop(governor.acquire("textfiles.com"));
+                start.elapsed()
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        drop(held);
+        assert!(waiter.join().unwrap() >= Duration::from_millis(40));
+    }
+}
@@ -0,0 +1,112 @@
+//! Drives an `App` without a terminal or network, for tests
+//!
+//! Pages come from a `MemorySource` standing in for textfiles.com, keys are
+//! fed in the keymap's own notation, and frames are drawn to a
+//! `TestBackend` and compared with text snapshots in `src/snapshots`. Run
+//! with `UPDATE_SNAPSHOTS=1` to rewrite them after an intended change.
+
+use crate::browser::Browser;
+use crate::config::Config;
+use crate::keymap;
+use crate::source::{MemorySource, Source, Sources};
+use crate::ui::App;
+use crossterm::event::KeyEvent;
+use ratatui::{backend::TestBackend, buffer::Buffer, Frame, Terminal};
+use std::sync::Arc;
+use std::time::Duration;
+use std::{env, fs, path::PathBuf, thread};
+
+pub const HOME: &str = "http://textfiles.com/directory.html";
+
+const DIRECTORY: &str = r#"<HTML><HEAD><TITLE>T E X T F I L E S</TITLE></HEAD><BODY>
+<TABLE>
+<TR><TD><B><A HREF="hacking">HACKING</A></B><BR><I>Breaking into systems</I></TD>
+<TD><B><A HREF="humor">HUMOR</A></B><BR><I>Jokes and parodies</I></TD></TR>
+</TABLE></BODY></HTML>"#;
+
+const HACKING: &str = r#"<HTML><HEAD><TITLE>Hacking</TITLE></HEAD><BODY>
+<TABLE>
+<TR><TD><B>Filename</B></TD><TD><B>Size</B></TD><TD><B>Description</B></TD></TR>
+<TR><TD><A HREF="phreak.txt">phreak.txt</A></TD><TD>180</TD><TD>Notes on blue boxes</TD></TR>
+<TR><TD><A HREF="unix.txt">unix.txt</A></TD><TD>2048</TD><TD>Unix for the curious</TD></TR>
+</TABLE></BODY></HTML>"#;
+
+const PHREAK: &str = "\
+BLUE BOXES
+==========
+
+A 2600 Hz tone seizes the trunk.
+See http://textfiles.com/phreak/ for more.
+";
+
+/// A small textfiles.com: the directory page, one listing and a text file.
+pub fn site() -> MemorySource {
+    MemorySource::new()
+        .with(HOME, DIRECTORY, Some("text/html"))
+        .with("http://textfiles.com/hacking/", HACKING, Some("text/html"))
+        .with("http://textfiles.com/hacking/phreak.txt", PHREAK, Some("text/plain"))
+}
+
+/// An app serving `source` for http(s) URLs, with the home page loaded.
+pub fn app(source: MemorySource) -> App {
+    app_with(Arc::new(source))
+}
+
+/// Like [`app`], for sources that do more than hand out files.
+pub fn app_with(source: Arc<dyn Source>) -> App {
+    let config = Config { home_url: HOME.to_string(), ..Config::default() };
+    let mut sources = Sources::default();
+    sources.register("http", source.clone());
+    sources.register("https", source);
+    let mut app = App::with_browser(&config, Browser::with_sources(&config, sources)).unwrap();
+    app.load_home().unwrap();
+    settle(&mut app);
+    app
+}
+
+/// Press `keys`, written like key bindings: `jj<Enter>`, `<C-f>`, letting
+/// any page load each one starts finish.
+pub fn press(app: &mut App, keys: &str) {
+    for key in keymap::parse_keys(keys).unwrap() {
+        app.handle_key(KeyEvent::new(key.code, key.modifiers)).unwrap();
+        settle(app);
+    }
+}
+
+/// Wait for the page loads in flight, if any, and apply them.
+pub fn settle(app: &mut App) {
+    while !app.loads.is_empty() {
+        app.poll_loads();
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Draw a `width`x`height` frame with `draw` and return it as text.
+pub fn render(app: &mut App, width: u16, height: u16, draw: impl FnOnce(&mut Frame, &mut App)) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    app.update_view_width(width);
+    terminal.draw(|f| draw(f, app)).unwrap();
+    to_text(terminal.backend().buffer())
+}
+
+fn to_text(buffer: &Buffer) -> String {
+    let width = buffer.area.width as usize;
+    buffer.content.chunks(width)
+        .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>().trim_end().to_string() + "\n")
+        .collect()
+}
+
+/// Compare `actual` with the saved snapshot `name`. A missing snapshot
+/// fails; set `UPDATE_SNAPSHOTS` to record it (or any that changed).
+pub fn assert_snapshot(name: &str, actual: &str) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "src", "snapshots", &format!("{name}.txt")].iter().collect();
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let Ok(expected) = fs::read_to_string(&path) else {
+        panic!("no snapshot {name} at {}; run with UPDATE_SNAPSHOTS=1 to record it\n{actual}", path.display());
+    };
+    assert!(expected == actual, "snapshot {name} differs\n--- expected\n{expected}--- actual\n{actual}");
+}
@@ -0,0 +1,381 @@
+//! HTML documents rendered as plain text
+
+use crate::reflow::{fill, wrap_line};
+use crate::wayback;
+use scraper::{ElementRef, Html, Node};
+
+#[derive(Debug, Clone)]
+pub enum DocBlock {
+    Heading(u8, String),
+    /// A paragraph or list item, filled to the width
+    Text { indent: usize, marker: Option<String>, text: String },
+    Preformatted(Vec<String>),
+    Rule,
+    Blank,
+}
+
+/// An HTML page as a list of blocks plus the links it refers to, which
+/// are numbered in the text and listed at the end.
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    pub blocks: Vec<DocBlock>,
+    pub links: Vec<String>,
+}
+
+impl Document {
+    pub fn parse(html: &str, base_url: &str) -> Self {
+        let document = Html::parse_document(html);
+        let mut walker = Walker { base_url, ..Walker::default() };
+        walker.walk(document.root_element());
+        walker.flush();
+        walker.doc
+    }
+
+    pub fn render(&self, width: usize) -> Vec<String> {
+        let width = width.max(10);
+        let mut out: Vec<String> = Vec::new();
+        for block in &self.blocks {
+            match block {
+                DocBlock::Heading(level, text) => {
+                    let text = if *level == 1 { text.to_uppercase() } else { text.clone() };
+                    let words: Vec<String> = text.split_whitespace().map(String::from).collect();
+                    let lines = fill(&words, 0, 0, width);
+                    let underline = match level {
+                        1 => Some('='),
+                        2 => Some('-'),
+                        _ => None,
+                    };
+                    let len = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+                    out.extend(lines);
+                    if let Some(c) = underline {
+                        out.push(c.to_string().repeat(len));
+                    }
+                }
+                DocBlock::Text { indent, marker, text } => {
+                    let mut words: Vec<String> = Vec::new();
+                    words.extend(marker.clone());
+                    words.extend(text.split_whitespace().map(String::from));
+                    let hanging = indent + marker.as_ref().map_or(0, |m| m.chars().count() + 1);
+                    out.extend(fill(&words, *indent, hanging, width));
+                }
+                DocBlock::Preformatted(lines) => {
+                    out.extend(lines.iter().flat_map(|l| wrap_line(l, width)));
+                }
+                DocBlock::Rule => out.push("-".repeat(width)),
+                DocBlock::Blank => {
+                    if out.last().is_some_and(|l| !l.is_empty()) {
+                        out.push(String::new());
+                    }
+                }
+            }
+        }
+
+        if !self.links.is_empty() {
+            if out.last().is_some_and(|l| !l.is_empty()) {
+                out.push(String::new());
+            }
+            out.push("Links".to_string());
+            out.push("-----".to_string());
+            for (i, url) in self.links.iter().enumerate() {
+                out.extend(wrap_line(&format!("[{}] {}", i + 1, url), width));
+            }
+        }
+        while out.last().is_some_and(|l| l.is_empty()) {
+            out.pop();
+        }
+        out
+    }
+}
+
+#[derive(Default)]
+struct Walker<'a> {
+    base_url: &'a str,
+    doc: Document,
+    /// Inline text of the block being built
+    inline: String,
+    indent: usize,
+    /// One entry per open list: `None` for bullets, `Some(n)` for numbers
+    lists: Vec<Option<usize>>,
+    marker: Option<String>,
+}
+
+impl Walker<'_> {
+    fn walk(&mut self, element: ElementRef) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => self.push_text(text),
+                Node::Element(_) => {
+                    if let Some(el) = ElementRef::wrap(child) {
+                        self.element(el);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn element(&mut self, el: ElementRef) {
+        let name = el.value().name();
+        match name {
+            "script" | "style" | "head" | "title" | "noscript" => {}
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.flush();
+                self.walk(el);
+                let text = std::mem::take(&mut self.inline).trim().to_string();
+                if !text.is_empty() {
+                    self.blank();
+                    self.doc.blocks.push(DocBlock::Heading(name[1..].parse().unwrap_or(3), text));
+                    self.blank();
+                }
+            }
+            "br" => self.flush(),
+            "hr" => {
+                self.flush();
+                self.doc.blocks.push(DocBlock::Rule);
+            }
+            "pre" | "xmp" | "plaintext" | "listing" => {
+                self.flush();
+                let text: String = el.text().collect();
+                let lines = text.trim_matches('\n').lines().map(|l| l.trim_end().to_string()).collect();
+                self.blank();
+                self.doc.blocks.push(DocBlock::Preformatted(lines));
+                self.blank();
+            }
+            "ul" | "ol" | "menu" | "dir" => {
+                self.flush();
+                if self.lists.is_empty() {
+                    self.blank();
+                }
+                self.lists.push((name == "ol").then_some(0));
+                self.walk(el);
+                self.flush();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank();
+                }
+            }
+            "li" => {
+                self.flush();
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{n}.")
+                    }
+                    _ => "*".to_string(),
+                };
+                self.marker = Some(marker);
+                self.walk(el);
+                self.flush();
+            }
+            "blockquote" | "dd" => {
+                self.flush();
+                self.indent += 4;
+                self.walk(el);
+                self.flush();
+                self.indent -= 4;
+            }
+            "p" | "div" | "center" | "section" | "article" | "address" | "table" | "form" | "dl" => {
+                self.flush();
+                self.blank();
+                self.walk(el);
+                self.flush();
+                self.blank();
+            }
+            "tr" | "dt" => {
+                self.flush();
+                self.walk(el);
+                self.flush();
+            }
+            "td" | "th" => {
+                self.walk(el);
+                self.inline.push_str("  ");
+            }
+            "img" => {
+                if let Some(alt) = el.value().attr("alt").filter(|a| !a.trim().is_empty()) {
+                    self.push_text(&format!("[{}]", alt.trim()));
+                }
+            }
+            "a" => {
+                self.walk(el);
+                let href = el.value().attr("href").map(str::trim).unwrap_or_default();
+                if !href.is_empty() && !href.starts_with('#') && !href.starts_with("javascript:") {
+                    let url = resolve_url(self.base_url, href);
+                    let n = match self.doc.links.iter().position(|l| *l == url) {
+                        Some(i) => i + 1,
+                        None => {
+                            self.doc.links.push(url);
+                            self.doc.links.len()
+                        }
+                    };
+                    self.inline.push_str(&format!("[{n}]"));
+                }
+            }
+            _ => self.walk(el),
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if text.starts_with(char::is_whitespace) && !self.inline.ends_with(' ') {
+            self.inline.push(' ');
+        }
+        self.inline.push_str(&text.split_whitespace().collect::<Vec<_>>().join(" "));
+        if text.ends_with(char::is_whitespace) && !text.trim().is_empty() {
+            self.inline.push(' ');
+        }
+    }
+
+    /// Finish the current paragraph or list item, if it has any text.
+    fn flush(&mut self) {
+        let text = std::mem::take(&mut self.inline).trim().to_string();
+        if text.is_empty() {
+            return;
+        }
+        let depth = self.lists.len().saturating_sub(1);
+        let indent = self.indent + if self.lists.is_empty() { 0 } else { 2 + depth * 3 };
+        let marker = self.marker.take();
+        self.doc.blocks.push(DocBlock::Text { indent, marker, text });
+    }
+
+    fn blank(&mut self) {
+        if !matches!(self.doc.blocks.last(), None | Some(DocBlock::Blank)) {
+            self.doc.blocks.push(DocBlock::Blank);
+        }
+    }
+}
+
+/// Resolve `href` against the page it appeared on. Links on Wayback
+/// snapshots resolve to snapshots from the same time.
+pub fn resolve_url(base: &str, href: &str) -> String {
+    if has_scheme(href) {
+        return href.to_string();
+    }
+    if let Some(snapshot) = wayback::parse(base) {
+        let prefix = &base[..base.len() - snapshot.original.len()];
+        return format!("{prefix}{}", resolve_url(snapshot.original, href));
+    }
+    let (scheme, rest) = base.split_once("://").unwrap_or(("http", base));
+    let host = rest.split('/').next().unwrap_or_default();
+    if let Some(path) = href.strip_prefix("//") {
+        return format!("{scheme}://{path}");
+    }
+
+    // Only the path is resolved; the query and fragment are kept as they are
+    let (path, suffix) = href.find(['?', '#']).map_or((href, ""), |i| href.split_at(i));
+    let base_path = rest[host.len()..].split(['?', '#']).next().unwrap_or_default();
+    let mut segments: Vec<&str> = if path.starts_with('/') {
+        Vec::new()
+    } else if path.is_empty() {
+        // Another query on the same page
+        base_path.split('/').filter(|s| !s.is_empty()).collect()
+    } else {
+        // Directory of the base page
+        let dir = &base_path[..base_path.rfind('/').map_or(0, |i| i + 1)];
+        dir.split('/').filter(|s| !s.is_empty()).collect()
+    };
+    let trailing = if path.is_empty() { base_path.ends_with('/') } else { path.ends_with('/') };
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            part => segments.push(part),
+        }
+    }
+    let mut url = format!("{scheme}://{host}/{}", segments.join("/"));
+    if trailing && !url.ends_with('/') {
+        url.push('/');
+    }
+    url.push_str(suffix);
+    url
+}
+
+/// Whether `href` is absolute, e.g. `http:`, `mailto:` or `gopher:`,
+/// rather than a path that happens to contain a colon.
+fn has_scheme(href: &str) -> bool {
+    href.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_blocks_and_numbers_links() {
+        let html = r#"<html><head><title>Skipped</title></head><body>
+            <h1>Blue boxes</h1>
+            <p>See <a href="box.txt">the notes</a> and <a href="../">the index</a>,
+            or <a href="box.txt">the notes</a> again.</p>
+            <ul><li>Whistles<li>Tone dialers<ol><li>Radio Shack</ol></ul>
+            <pre>
+  2600   1
+  1700   2
+</pre>
+            <hr><script>alert(1)</script>
+            </body></html>"#;
+        let doc = Document::parse(html, "http://textfiles.com/phreak/index.html");
+        assert_eq!(doc.links, ["http://textfiles.com/phreak/box.txt", "http://textfiles.com/"]);
+        assert_eq!(doc.render(30), [
+            "BLUE BOXES",
+            "==========",
+            "",
+            "See the notes[1] and the",
+            "index[2], or the notes[1]",
+            "again.",
+            "",
+            "  * Whistles",
+            "  * Tone dialers",
+            "     1. Radio Shack",
+            "",
+            "  2600   1",
+            "  1700   2",
+            "",
+            "------------------------------",
+            "",
+            "Links",
+            "-----",
+            "[1] http://textfiles.com/phrea",
+            "k/box.txt",
+            "[2] http://textfiles.com/",
+        ]);
+    }
+
+    #[test]
+    fn resolves_relative_links() {
+        let base = "http://textfiles.com/hacking/internet/index.html";
+        for (href, url) in [
+            ("faq.txt", "http://textfiles.com/hacking/internet/faq.txt"),
+            ("./faq.txt", "http://textfiles.com/hacking/internet/faq.txt"),
+            ("../phreak/", "http://textfiles.com/hacking/phreak/"),
+            ("../../../../etc", "http://textfiles.com/etc"),
+            ("/bbs/", "http://textfiles.com/bbs/"),
+            ("//web.textfiles.com/ezines/", "http://web.textfiles.com/ezines/"),
+            ("ftp://ftp.std.com/pub/", "ftp://ftp.std.com/pub/"),
+            ("mailto:jason@textfiles.com", "mailto:jason@textfiles.com"),
+            ("go.cgi?to=http://example.com/", "http://textfiles.com/hacking/internet/go.cgi?to=http://example.com/"),
+            ("?page=2#top", "http://textfiles.com/hacking/internet/index.html?page=2#top"),
+        ] {
+            assert_eq!(resolve_url(base, href), url, "{href}");
+        }
+        assert_eq!(resolve_url("https://textfiles.com/", "//x.org/"), "https://x.org/");
+    }
+
+    #[test]
+    fn resolves_within_snapshots_and_local_files() {
+        let snapshot = "https://web.archive.org/web/19990208000000/http://www.textfiles.com/hacking/";
+        assert_eq!(
+            resolve_url(snapshot, "../humor/"),
+            "https://web.archive.org/web/19990208000000/http://www.textfiles.com/humor/",
+        );
+        assert_eq!(
+            resolve_url(snapshot, "/news/"),
+            "https://web.archive.org/web/19990208000000/http://www.textfiles.com/news/",
+        );
+        assert_eq!(resolve_url("file:///srv/textfiles/hacking/index.html", "phreak.txt"), "file:///srv/textfiles/hacking/phreak.txt");
+        assert_eq!(resolve_url("file:///srv/textfiles/hacking/", "../humor/"), "file:///srv/textfiles/humor/");
+        assert_eq!(resolve_url("file:///srv/textfiles/", "/tmp/x.txt"), "file:///tmp/x.txt");
+    }
+}
@@ -0,0 +1,522 @@
+//! Key bindings, presets and key sequence handling
+
+use crate::config::Config;
+use crate::ui::Mode;
+use anyhow::{bail, Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    Open,
+    Back,
+    Refresh,
+    ToggleWrap,
+    CycleTheme,
+    TogglePreview,
+    OpenInNewTab,
+    NextTab,
+    PrevTab,
+    CloseTab,
+    ToggleHeader,
+    NextLink,
+    PrevLink,
+    FollowLink,
+    Snapshots,
+    Help,
+}
+
+impl Action {
+    /// Every action, in the order the help overlay lists them.
+    pub const ALL: &[Action] = &[
+        Action::Up, Action::Down, Action::PageUp, Action::PageDown, Action::Top, Action::Bottom,
+        Action::Open, Action::Back, Action::Refresh, Action::ToggleWrap, Action::TogglePreview,
+        Action::OpenInNewTab, Action::NextTab, Action::PrevTab, Action::CloseTab,
+        Action::NextLink, Action::PrevLink, Action::FollowLink, Action::Snapshots,
+        Action::ToggleHeader, Action::CycleTheme, Action::Help, Action::Quit,
+    ];
+
+    pub fn description(self, mode: Mode) -> &'static str {
+        match (self, mode) {
+            (Action::Quit, _) => "Quit",
+            (Action::Up, Mode::Browser) => "Previous entry",
+            (Action::Up, Mode::Viewer) => "Scroll up",
+            (Action::Down, Mode::Browser) => "Next entry",
+            (Action::Down, Mode::Viewer) => "Scroll down",
+            (Action::PageUp, _) => "Page up",
+            (Action::PageDown, _) => "Page down",
+            (Action::Top, _) => "Jump to top (or to N with a count)",
+            (Action::Bottom, _) => "Jump to bottom (or to N with a count)",
+            (Action::Open, _) => "Open selected entry",
+            (Action::Back, Mode::Browser) => "Go back",
+            (Action::Back, Mode::Viewer) => "Close file and go back",
+            (Action::Refresh, _) => "Reload page",
+            (Action::ToggleWrap, _) => "Toggle paragraph reflow",
+            (Action::CycleTheme, _) => "Cycle color theme",
+            (Action::TogglePreview, _) => "Toggle preview pane",
+            (Action::OpenInNewTab, _) => "Open selected entry in a new tab",
+            (Action::NextTab, _) => "Next tab",
+            (Action::PrevTab, _) => "Previous tab",
+            (Action::CloseTab, _) => "Close tab",
+            (Action::ToggleHeader, _) => "Collapse or expand the header",
+            (Action::NextLink, _) => "Focus next link",
+            (Action::PrevLink, _) => "Focus previous link",
+            (Action::FollowLink, _) => "Follow focused link",
+            (Action::Snapshots, _) => "Browse Wayback Machine snapshots of this page",
+            (Action::Help, _) => "Show this help",
+        }
+    }
+}
+
+/// Where a binding applies. Mode bindings win over global ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Global,
+    Browser,
+    Viewer,
+}
+
+impl Scope {
+    fn matches(self, mode: Mode) -> bool {
+        match self {
+            Scope::Global => true,
+            Scope::Browser => mode == Mode::Browser,
+            Scope::Viewer => mode == Mode::Viewer,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        let mut modifiers = event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        // Shift is already part of the character for printable keys and BackTab
+        if !matches!(event.code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers |= event.modifiers & KeyModifiers::SHIFT;
+        }
+        Self { code: event.code, modifiers }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub scope: Scope,
+    pub keys: Vec<Key>,
+    pub action: Action,
+}
+
+/// Per-scope overrides from the config file, e.g. `"<C-d>" = "page_down"`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyOverrides {
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub global: BTreeMap<String, Action>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub browser: BTreeMap<String, Action>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub viewer: BTreeMap<String, Action>,
+}
+
+pub const PRESETS: &[&str] = &["vim", "emacs", "less"];
+
+pub struct Keymap {
+    pub bindings: Vec<Binding>,
+    pending: Vec<Key>,
+    count: Option<usize>,
+}
+
+impl Keymap {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut keymap = Self::preset(&config.keymap)?;
+        let scopes = [
+            (Scope::Global, &config.keys.global),
+            (Scope::Browser, &config.keys.browser),
+            (Scope::Viewer, &config.keys.viewer),
+        ];
+        for (scope, overrides) in scopes {
+            for (keys, action) in overrides {
+                let keys = parse_keys(keys).with_context(|| format!("Invalid key binding {keys:?}"))?;
+                keymap.bind(scope, keys, *action);
+            }
+        }
+        Ok(keymap)
+    }
+
+    pub fn preset(name: &str) -> Result<Self> {
+        use Action::*;
+        use Scope::*;
+        let table: &[(Scope, &str, Action)] = match name {
+            "vim" => &[
+                (Global, "q", Quit), (Global, "<C-c>", Quit),
+                (Global, "r", Refresh), (Global, "T", CycleTheme),
+                (Global, "gt", NextTab), (Global, "gT", PrevTab), (Global, "<C-w>", CloseTab),
+                (Global, "H", ToggleHeader), (Global, "W", Snapshots),
+                (Global, "?", Help), (Global, "<F1>", Help),
+                (Browser, "k", Up), (Browser, "<Up>", Up),
+                (Browser, "j", Down), (Browser, "<Down>", Down),
+                (Browser, "<PageUp>", PageUp), (Browser, "<C-b>", PageUp),
+                (Browser, "<PageDown>", PageDown), (Browser, "<C-f>", PageDown),
+                (Browser, "gg", Top), (Browser, "<Home>", Top),
+                (Browser, "G", Bottom), (Browser, "<End>", Bottom),
+                (Browser, "<Enter>", Open), (Browser, "l", Open), (Browser, "<Right>", Open),
+                (Browser, "<Backspace>", Back), (Browser, "h", Back),
+                (Browser, "<Left>", Back), (Browser, "<Esc>", Back),
+                (Browser, "p", TogglePreview), (Browser, "t", OpenInNewTab),
+                (Viewer, "k", Up), (Viewer, "<Up>", Up),
+                (Viewer, "j", Down), (Viewer, "<Down>", Down),
+                (Viewer, "<PageUp>", PageUp), (Viewer, "b", PageUp), (Viewer, "<C-b>", PageUp),
+                (Viewer, "<PageDown>", PageDown), (Viewer, "<Space>", PageDown), (Viewer, "<C-f>", PageDown),
+                (Viewer, "gg", Top), (Viewer, "<Home>", Top),
+                (Viewer, "G", Bottom), (Viewer, "<End>", Bottom),
+                (Viewer, "w", ToggleWrap),
+                (Viewer, "<Tab>", NextLink), (Viewer, "<S-Tab>", PrevLink), (Viewer, "<Enter>", FollowLink),
+                (Viewer, "q", Back), (Viewer, "h", Back), (Viewer, "<Backspace>", Back),
+                (Viewer, "<Left>", Back), (Viewer, "<Esc>", Back),
+            ],
+            "emacs" => &[
+                (Global, "<C-x><C-c>", Quit), (Global, "<C-c>", Quit),
+                (Global, "g", Refresh), (Global, "T", CycleTheme),
+                (Global, "<C-x>o", NextTab), (Global, "<C-x>O", PrevTab), (Global, "<C-x>k", CloseTab),
+                (Global, "<C-x>h", ToggleHeader), (Global, "<C-x>w", Snapshots),
+                (Global, "?", Help), (Global, "<F1>", Help),
+                (Global, "<C-p>", Up), (Global, "<Up>", Up),
+                (Global, "<C-n>", Down), (Global, "<Down>", Down),
+                (Global, "<M-v>", PageUp), (Global, "<PageUp>", PageUp),
+                (Global, "<C-v>", PageDown), (Global, "<PageDown>", PageDown),
+                (Global, "<M-<>", Top), (Global, "<Home>", Top),
+                (Global, "<M->>", Bottom), (Global, "<End>", Bottom),
+                (Global, "<Backspace>", Back), (Global, "<Left>", Back), (Global, "<C-b>", Back),
+                (Browser, "q", Quit),
+                (Browser, "<Enter>", Open), (Browser, "<C-f>", Open), (Browser, "<Right>", Open),
+                (Browser, "p", TogglePreview), (Browser, "<C-x>t", OpenInNewTab),
+                (Viewer, "<Space>", PageDown), (Viewer, "<M-q>", ToggleWrap),
+                (Viewer, "<Tab>", NextLink), (Viewer, "<S-Tab>", PrevLink), (Viewer, "<Enter>", FollowLink),
+                (Viewer, "q", Back),
+            ],
+            "less" => &[
+                (Global, "Q", Quit), (Global, "<C-c>", Quit),
+                (Global, "R", Refresh), (Global, "T", CycleTheme),
+                (Global, "]", NextTab), (Global, "[", PrevTab), (Global, "x", CloseTab),
+                (Global, "H", ToggleHeader), (Global, "W", Snapshots),
+                (Global, "?", Help), (Global, "<F1>", Help),
+                (Global, "k", Up), (Global, "y", Up), (Global, "<Up>", Up),
+                (Global, "j", Down), (Global, "e", Down), (Global, "<Down>", Down),
+                (Global, "b", PageUp), (Global, "<PageUp>", PageUp),
+                (Global, "f", PageDown), (Global, "<Space>", PageDown), (Global, "<PageDown>", PageDown),
+                (Global, "g", Top), (Global, "<", Top), (Global, "<Home>", Top),
+                (Global, "G", Bottom), (Global, ">", Bottom), (Global, "<End>", Bottom),
+                (Global, "<Backspace>", Back), (Global, "<Left>", Back),
+                (Browser, "q", Quit),
+                (Browser, "<Enter>", Open), (Browser, "<Right>", Open),
+                (Browser, "p", TogglePreview), (Browser, "t", OpenInNewTab),
+                (Viewer, "<Enter>", Down), (Viewer, "w", ToggleWrap),
+                (Viewer, "<Tab>", NextLink), (Viewer, "<S-Tab>", PrevLink), (Viewer, "o", FollowLink),
+                (Viewer, "q", Back),
+            ],
+            _ => bail!("Unknown keymap {name:?} (expected one of: {})", PRESETS.join(", ")),
+        };
+
+        let mut keymap = Self { bindings: Vec::new(), pending: Vec::new(), count: None };
+        for (scope, keys, action) in table {
+            keymap.bind(*scope, parse_keys(keys)?, *action);
+        }
+        Ok(keymap)
+    }
+
+    /// Bind `keys` in `scope`, replacing any existing binding for them.
+    pub fn bind(&mut self, scope: Scope, keys: Vec<Key>, action: Action) {
+        self.bindings.retain(|b| !(b.scope == scope && b.keys == keys));
+        self.bindings.push(Binding { scope, keys, action });
+    }
+
+    /// Feed one key press. Returns the action once a full sequence is
+    /// matched, along with any numeric count typed before it.
+    pub fn feed(&mut self, event: KeyEvent, mode: Mode) -> Option<(Action, Option<usize>)> {
+        let key = Key::from(event);
+
+        if key.code == KeyCode::Esc && (!self.pending.is_empty() || self.count.is_some()) {
+            self.pending.clear();
+            self.count = None;
+            return None;
+        }
+
+        if self.pending.is_empty()
+            && key.modifiers.is_empty()
+            && let KeyCode::Char(c @ '0'..='9') = key.code
+            && (c != '0' || self.count.is_some())
+            && self.lookup(&[key], mode).is_none()
+        {
+            let digit = c as usize - '0' as usize;
+            self.count = Some(self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+            return None;
+        }
+
+        self.pending.push(key);
+        if let Some(action) = self.lookup(&self.pending, mode) {
+            self.pending.clear();
+            return Some((action, self.count.take()));
+        }
+        if self.is_prefix(&self.pending, mode) {
+            return None;
+        }
+
+        // Dead end: drop the sequence, but let its last key start a new one
+        let retry = self.pending.len() > 1;
+        self.pending.clear();
+        if retry {
+            return self.feed(event, mode);
+        }
+        self.count = None;
+        None
+    }
+
+    /// Keys that trigger each action in `mode`, skipping global bindings
+    /// shadowed by a mode binding.
+    pub fn bindings_for(&self, mode: Mode) -> Vec<(Action, Vec<String>)> {
+        let active: Vec<&Binding> = self.bindings.iter()
+            .filter(|b| b.scope.matches(mode))
+            .filter(|b| b.scope != Scope::Global || self.lookup(&b.keys, mode) == Some(b.action))
+            .collect();
+        Action::ALL.iter()
+            .map(|&action| {
+                let keys = active.iter()
+                    .filter(|b| b.action == action)
+                    .map(|b| b.keys.iter().map(|k| format_key(*k)).collect())
+                    .collect();
+                (action, keys)
+            })
+            .filter(|(_, keys): &(Action, Vec<String>)| !keys.is_empty())
+            .collect()
+    }
+
+    /// First key sequence bound to `action` in `mode`, for hints.
+    pub fn hint(&self, action: Action, mode: Mode) -> Option<String> {
+        self.bindings_for(mode).into_iter()
+            .find(|(a, _)| *a == action)
+            .and_then(|(_, keys)| keys.into_iter().next())
+    }
+
+    /// Count and partial sequence typed so far, for the status bar.
+    pub fn pending(&self) -> String {
+        let count = self.count.map(|n| n.to_string()).unwrap_or_default();
+        let keys: String = self.pending.iter().map(|k| format_key(*k)).collect();
+        format!("{count}{keys}")
+    }
+
+    fn lookup(&self, keys: &[Key], mode: Mode) -> Option<Action> {
+        let find = |global: bool| {
+            self.bindings.iter()
+                .filter(|b| (b.scope == Scope::Global) == global && b.scope.matches(mode))
+                .find(|b| b.keys == keys)
+                .map(|b| b.action)
+        };
+        find(false).or_else(|| find(true))
+    }
+
+    fn is_prefix(&self, keys: &[Key], mode: Mode) -> bool {
+        self.bindings.iter()
+            .filter(|b| b.scope.matches(mode))
+            .any(|b| b.keys.len() > keys.len() && b.keys.starts_with(keys))
+    }
+}
+
+/// Parse vim-style key notation: `gg`, `<C-f>`, `<M-v>`, `<Space>`, `<C-x><C-c>`.
+pub fn parse_keys(s: &str) -> Result<Vec<Key>> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '<' && i + 1 < chars.len() && chars[i + 1..].contains(&'>') && chars.len() > 2 {
+            let mut modifiers = KeyModifiers::NONE;
+            let mut j = i + 1;
+            while j + 2 < chars.len() && chars[j + 1] == '-' {
+                modifiers |= match chars[j] {
+                    'C' => KeyModifiers::CONTROL,
+                    'M' | 'A' => KeyModifiers::ALT,
+                    'S' => KeyModifiers::SHIFT,
+                    _ => break,
+                };
+                j += 2;
+            }
+            // The key name is at least one character, so `<M->>` is Alt+>
+            let end = match chars[j + 1..].iter().position(|&c| c == '>') {
+                Some(n) => j + 1 + n,
+                None => bail!("unterminated '<' in {s:?}"),
+            };
+            let name: String = chars[j..end].iter().collect();
+            let code = match name.to_lowercase().as_str() {
+                "enter" | "cr" | "return" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "space" => KeyCode::Char(' '),
+                "tab" => KeyCode::Tab,
+                "bs" | "backspace" => KeyCode::Backspace,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "del" | "delete" => KeyCode::Delete,
+                "lt" => KeyCode::Char('<'),
+                "gt" => KeyCode::Char('>'),
+                f if f.len() > 1 && f.starts_with('f') && f[1..].parse::<u8>().is_ok() => {
+                    KeyCode::F(f[1..].parse()?)
+                }
+                _ if name.chars().count() == 1 => {
+                    let c = name.chars().next().unwrap_or(' ');
+                    if modifiers.contains(KeyModifiers::CONTROL) { KeyCode::Char(c.to_ascii_lowercase()) } else { KeyCode::Char(c) }
+                }
+                _ => bail!("unknown key <{name}> in {s:?}"),
+            };
+            // Terminals report Shift+Tab as BackTab
+            let code = if code == KeyCode::Tab && modifiers.contains(KeyModifiers::SHIFT) { KeyCode::BackTab } else { code };
+            if matches!(code, KeyCode::Char(_) | KeyCode::BackTab) {
+                modifiers.remove(KeyModifiers::SHIFT);
+            }
+            keys.push(Key { code, modifiers });
+            i = end + 1;
+        } else {
+            keys.push(Key { code: KeyCode::Char(chars[i]), modifiers: KeyModifiers::NONE });
+            i += 1;
+        }
+    }
+    if keys.is_empty() {
+        bail!("empty key sequence");
+    }
+    Ok(keys)
+}
+
+/// Inverse of [`parse_keys`] for a single key.
+pub fn format_key(key: Key) -> String {
+    let name = match key.code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) if key.modifiers.is_empty() => return c.to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => "Enter".into(),
+        KeyCode::Esc => "Esc".into(),
+        KeyCode::Tab => "Tab".into(),
+        KeyCode::BackTab => "S-Tab".into(),
+        KeyCode::Backspace => "BS".into(),
+        KeyCode::Up => "Up".into(),
+        KeyCode::Down => "Down".into(),
+        KeyCode::Left => "Left".into(),
+        KeyCode::Right => "Right".into(),
+        KeyCode::Home => "Home".into(),
+        KeyCode::End => "End".into(),
+        KeyCode::PageUp => "PageUp".into(),
+        KeyCode::PageDown => "PageDown".into(),
+        KeyCode::Delete => "Del".into(),
+        KeyCode::F(n) => format!("F{n}"),
+        other => format!("{other:?}"),
+    };
+    let mut prefix = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        prefix.push_str("C-");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        prefix.push_str("M-");
+    }
+    if key.modifiers.contains(KeyModifiers::SHIFT) {
+        prefix.push_str("S-");
+    }
+    format!("<{prefix}{name}>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(keymap: &mut Keymap, keys: &str, mode: Mode) -> Vec<(Action, Option<usize>)> {
+        parse_keys(keys).unwrap().into_iter()
+            .filter_map(|key| keymap.feed(KeyEvent::new(key.code, key.modifiers), mode))
+            .collect()
+    }
+
+    #[test]
+    fn parses_key_notation() {
+        let ctrl = |c| Key { code: KeyCode::Char(c), modifiers: KeyModifiers::CONTROL };
+        assert_eq!(parse_keys("<C-x><C-c>").unwrap(), [ctrl('x'), ctrl('c')]);
+        assert_eq!(parse_keys("<C-F>").unwrap(), [ctrl('f')]);
+        assert_eq!(parse_keys("<M->>").unwrap(), [Key { code: KeyCode::Char('>'), modifiers: KeyModifiers::ALT }]);
+        assert_eq!(parse_keys("<S-Tab>").unwrap(), [Key { code: KeyCode::BackTab, modifiers: KeyModifiers::NONE }]);
+        assert_eq!(parse_keys("<F12>").unwrap()[0].code, KeyCode::F(12));
+        assert_eq!(parse_keys("gg").unwrap().len(), 2);
+        assert_eq!(parse_keys("<").unwrap()[0].code, KeyCode::Char('<'));
+        assert!(parse_keys("").is_err());
+        assert!(parse_keys("<Nope>").is_err());
+        for key in parse_keys("<C-x>o<Space><S-Tab><F1>").unwrap() {
+            assert_eq!(parse_keys(&format_key(key)).unwrap(), [key]);
+        }
+    }
+
+    #[test]
+    fn collects_counts() {
+        let mut keymap = Keymap::preset("vim").unwrap();
+        assert_eq!(feed(&mut keymap, "12j", Mode::Viewer), [(Action::Down, Some(12))]);
+        assert_eq!(feed(&mut keymap, "j", Mode::Viewer), [(Action::Down, None)]);
+        // A leading zero isn't a count
+        assert_eq!(feed(&mut keymap, "0j", Mode::Viewer), [(Action::Down, None)]);
+        assert_eq!(feed(&mut keymap, "5<Esc>j", Mode::Viewer), [(Action::Down, None)]);
+        let huge = feed(&mut keymap, "99999999999999999999999G", Mode::Viewer);
+        assert_eq!(huge, [(Action::Bottom, Some(usize::MAX))]);
+    }
+
+    #[test]
+    fn matches_key_sequences() {
+        let mut keymap = Keymap::preset("vim").unwrap();
+        assert_eq!(feed(&mut keymap, "g", Mode::Browser), []);
+        assert_eq!(keymap.pending(), "g");
+        assert_eq!(feed(&mut keymap, "g", Mode::Browser), [(Action::Top, None)]);
+        assert_eq!(feed(&mut keymap, "3gt", Mode::Browser), [(Action::NextTab, Some(3))]);
+        // A dead end drops the sequence but lets its last key start over
+        assert_eq!(feed(&mut keymap, "gj", Mode::Browser), [(Action::Down, None)]);
+        assert_eq!(keymap.pending(), "");
+    }
+
+    #[test]
+    fn mode_bindings_shadow_global_ones() {
+        let mut keymap = Keymap::preset("less").unwrap();
+        assert_eq!(feed(&mut keymap, "q", Mode::Browser), [(Action::Quit, None)]);
+        assert_eq!(feed(&mut keymap, "q", Mode::Viewer), [(Action::Back, None)]);
+        keymap.bind(Scope::Viewer, parse_keys("j").unwrap(), Action::PageDown);
+        assert_eq!(feed(&mut keymap, "j", Mode::Viewer), [(Action::PageDown, None)]);
+        assert_eq!(feed(&mut keymap, "j", Mode::Browser), [(Action::Down, None)]);
+    }
+
+    #[test]
+    fn presets_cover_the_basics() {
+        let expect = [
+            ("vim", "<C-f>", "q", "gt"),
+            ("emacs", "<C-v>", "<C-x><C-c>", "<C-x>o"),
+            ("less", "<Space>", "Q", "]"),
+        ];
+        for (name, page_down, quit, next_tab) in expect {
+            let mut keymap = Keymap::preset(name).unwrap();
+            assert_eq!(feed(&mut keymap, page_down, Mode::Viewer), [(Action::PageDown, None)], "{name}");
+            assert_eq!(feed(&mut keymap, quit, Mode::Browser), [(Action::Quit, None)], "{name}");
+            assert_eq!(feed(&mut keymap, next_tab, Mode::Browser), [(Action::NextTab, None)], "{name}");
+            for mode in [Mode::Browser, Mode::Viewer] {
+                assert!(keymap.hint(Action::Help, mode).is_some(), "{name}");
+                assert!(keymap.hint(Action::Back, mode).is_some(), "{name}");
+            }
+        }
+        assert_eq!(PRESETS.len(), 3);
+        assert!(Keymap::preset("nano").is_err());
+    }
+}
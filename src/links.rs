@@ -0,0 +1,252 @@
+//! URL, email, FTP and BBS number detection in plain text
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkKind {
+    /// Somewhere on textfiles.com, opened in the browser itself
+    Textfiles,
+    Web,
+    Ftp,
+    Email,
+    /// A BBS dial-up number
+    Phone,
+}
+
+#[derive(Debug, Clone)]
+pub struct Link {
+    /// Index into the wrapped lines
+    pub line: usize,
+    /// Start column, in characters
+    pub col: usize,
+    pub text: String,
+    pub kind: LinkKind,
+    pub url: String,
+}
+
+/// Characters that may surround a link but are not part of it
+const TRIM: &[char] = &['<', '>', '(', ')', '[', ']', '{', '}', '"', '\'', ',', '.', ';', ':', '!', '?', '`'];
+
+pub fn find_links(lines: &[String]) -> Vec<Link> {
+    let mut links = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let mut found = find_in_line(line);
+        found.sort_by_key(|(col, ..)| *col);
+        // A number inside a URL is part of the URL
+        let mut end = 0;
+        for (col, text, kind, url) in found {
+            if col >= end {
+                end = col + text.chars().count();
+                links.push(Link { line: i, col, text, kind, url });
+            }
+        }
+    }
+    links
+}
+
+fn find_in_line(line: &str) -> Vec<(usize, String, LinkKind, String)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut found = Vec::new();
+
+    // Whitespace-separated tokens: URLs, hosts and addresses
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && !chars[i].is_whitespace() {
+            i += 1;
+        }
+        let token: String = chars[start..i].iter().collect();
+        let lead = token.len() - token.trim_start_matches(TRIM).len();
+        let word = token.trim_matches(TRIM);
+        if let Some((kind, url)) = classify(word) {
+            let col = start + token[..lead].chars().count();
+            found.push((col, word.to_string(), kind, url));
+        }
+    }
+
+    found.extend(find_phones(&chars));
+    found
+}
+
+/// Decide whether a single word is a link, and where it points.
+fn classify(word: &str) -> Option<(LinkKind, String)> {
+    let lower = word.to_lowercase();
+    // Escapes would reach the terminal inside the OSC 8 sequence
+    if lower.len() < 5 || word.chars().any(char::is_control) {
+        return None;
+    }
+
+    let (kind, url) = if ["http://", "https://"].iter().any(|p| lower.starts_with(p)) {
+        (LinkKind::Web, word.to_string())
+    } else if ["ftp://", "gopher://", "telnet://"].iter().any(|p| lower.starts_with(p)) {
+        (LinkKind::Ftp, word.to_string())
+    } else if lower.starts_with("www.") && lower[4..].contains('.') {
+        (LinkKind::Web, format!("http://{word}"))
+    } else if lower.starts_with("ftp.") && lower[4..].contains('.') {
+        (LinkKind::Ftp, format!("ftp://{word}"))
+    } else if lower.starts_with("textfiles.com") {
+        (LinkKind::Web, format!("http://{word}"))
+    } else if is_email(word) {
+        (LinkKind::Email, format!("mailto:{word}"))
+    } else {
+        return None;
+    };
+
+    if kind == LinkKind::Web && is_textfiles(&url) {
+        return Some((LinkKind::Textfiles, url));
+    }
+    Some((kind, url))
+}
+
+fn is_textfiles(url: &str) -> bool {
+    let host = url.split("://").nth(1).unwrap_or_default()
+        .split('/').next().unwrap_or_default()
+        .to_lowercase();
+    host == "textfiles.com" || host.ends_with(".textfiles.com")
+}
+
+fn is_email(word: &str) -> bool {
+    let Some((local, domain)) = word.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && local.chars().all(|c| c.is_ascii_alphanumeric() || "._-+%!".contains(c))
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && domain.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+}
+
+/// North American style numbers: `555-1234`, `212-555-1234`,
+/// `(212) 555-1234`, `1-800-555-1234`, `212/555-1234`.
+fn find_phones(chars: &[char]) -> Vec<(usize, String, LinkKind, String)> {
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let boundary = i == 0 || !chars[i - 1].is_alphanumeric();
+        if !boundary || !(chars[i].is_ascii_digit() || chars[i] == '(') {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let mut end = i;
+        let mut j = i;
+        while j < chars.len() && (chars[j].is_ascii_digit() || "-()./ ".contains(chars[j])) {
+            // A single space only, and only after a closing paren
+            if chars[j] == ' ' && (j == 0 || chars[j - 1] != ')') {
+                break;
+            }
+            if chars[j].is_ascii_digit() {
+                end = j + 1;
+            }
+            j += 1;
+        }
+        let followed_by_word = chars.get(end).is_some_and(|c| c.is_alphanumeric());
+        let text: String = chars[start..end].iter().collect();
+        if !followed_by_word && let Some(digits) = phone_digits(&text) {
+            found.push((start, text, LinkKind::Phone, format!("tel:{digits}")));
+            i = end;
+        } else if chars[start] == '(' {
+            // Maybe just a bracketed local number
+            i = start + 1;
+        } else {
+            i = j.max(i + 1);
+        }
+    }
+    found
+}
+
+/// The dialable form of `text`, if it is a phone number: local numbers
+/// as-is, anything with an area code in international form. Area codes
+/// and exchanges never start with 0 or 1, which rules out most other
+/// numbers.
+fn phone_digits(text: &str) -> Option<String> {
+    let groups: Vec<&str> = text.split(|c: char| !c.is_ascii_digit())
+        .filter(|g| !g.is_empty())
+        .collect();
+    // The punctuation around the groups, e.g. "(N) N-N"
+    let mut shape = String::new();
+    for c in text.chars() {
+        if !c.is_ascii_digit() {
+            shape.push(c);
+        } else if !shape.ends_with('N') {
+            shape.push('N');
+        }
+    }
+    let lens: Vec<usize> = groups.iter().map(|g| g.len()).collect();
+    let area_or_exchange = |g: &str| g.starts_with(['2', '3', '4', '5', '6', '7', '8', '9']);
+    let digits: String = groups.concat();
+    match (lens.as_slice(), shape.as_str()) {
+        ([3, 4], "N-N") if area_or_exchange(groups[0]) => Some(digits),
+        ([3, 3, 4], "N-N-N" | "(N) N-N" | "(N)N-N" | "N/N-N" | "N.N.N")
+            if area_or_exchange(groups[0]) && area_or_exchange(groups[1]) => Some(format!("+1{digits}")),
+        ([1, 3, 3, 4], "N-N-N-N")
+            if groups[0] == "1" && area_or_exchange(groups[1]) && area_or_exchange(groups[2]) => Some(format!("+{digits}")),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Links found in `line`, as (text, url) pairs
+    fn links(line: &str) -> Vec<(String, String)> {
+        find_links(&[line.to_string()]).into_iter().map(|l| (l.text, l.url)).collect()
+    }
+
+    fn phones(line: &str) -> Vec<String> {
+        find_links(&[line.to_string()]).into_iter()
+            .filter(|l| l.kind == LinkKind::Phone)
+            .map(|l| l.url)
+            .collect()
+    }
+
+    #[test]
+    fn finds_urls_hosts_and_addresses() {
+        assert_eq!(links("See <http://www.2600.com/phone.html>."), [
+            ("http://www.2600.com/phone.html".to_string(), "http://www.2600.com/phone.html".to_string()),
+        ]);
+        assert_eq!(links("ftp.funet.fi, or mail jason@textfiles.com!"), [
+            ("ftp.funet.fi".to_string(), "ftp://ftp.funet.fi".to_string()),
+            ("jason@textfiles.com".to_string(), "mailto:jason@textfiles.com".to_string()),
+        ]);
+        let link = &find_links(&["  (textfiles.com/hacking/)".to_string()])[0];
+        assert_eq!((link.col, link.kind, link.url.as_str()), (3, LinkKind::Textfiles, "http://textfiles.com/hacking/"));
+        assert!(links("www.example or @home or a.b or mail@.com").is_empty());
+    }
+
+    #[test]
+    fn rejects_urls_with_control_characters() {
+        assert!(links("http://x\x1b]0;pwned\x07 and www.evil.com\x1b[2J").is_empty());
+        assert_eq!(links("http://x \x1b]0;pwned\x07").len(), 1);
+    }
+
+    #[test]
+    fn finds_phone_numbers() {
+        assert_eq!(phones("Call 555-1234 after 6pm"), ["tel:5551234"]);
+        assert_eq!(phones("(212) 555-1234 or 212/555-1234"), ["tel:+12125551234", "tel:+12125551234"]);
+        assert_eq!(phones("BBS: 1-800-555-1234, 300 baud"), ["tel:+18005551234"]);
+        assert_eq!(phones("Node 2 (555-1234)"), ["tel:5551234"]);
+        assert_eq!(phones("212.555.1234"), ["tel:+12125551234"]);
+    }
+
+    #[test]
+    fn ignores_other_numbers() {
+        for line in [
+            "pi is 314.1592",
+            "released 1985-1990",
+            "part 123-4567",
+            "call 555-12345",
+            "ISBN 0-201-555-1234",
+            "212-555.1234",
+            "model 555-1234B",
+            "http://example.com/555-1234",
+            "12/25/1985",
+        ] {
+            assert!(phones(line).is_empty(), "{line}");
+        }
+    }
+}
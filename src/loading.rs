@@ -0,0 +1,98 @@
+//! Page loads, and other slow requests, on a worker thread, so the UI
+//! keeps drawing while they run
+
+use crate::browser::{self, Loaded, Loader, Visit};
+use crate::progress::{self, Progress};
+use crate::source::ReadOptions;
+use anyhow::{anyhow, Result};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+/// Where a finished load goes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Destination {
+    /// The tab at this index, moving through its history as given
+    Tab(usize, Visit),
+    /// A new tab after the one at this index
+    NewTab(usize),
+    /// A picker of the Wayback snapshots of the URL, over the tab at this
+    /// index; the URL may itself be a snapshot
+    Snapshots(usize),
+}
+
+impl Destination {
+    /// The tab the load belongs to: the one it fills, or opens a tab after.
+    pub fn tab(self) -> usize {
+        match self {
+            Self::Tab(i, _) | Self::NewTab(i) | Self::Snapshots(i) => i,
+        }
+    }
+
+    /// Whether the load replaces what its tab shows, so a newer one for
+    /// the same tab makes it pointless.
+    pub fn in_place(self) -> bool {
+        !matches!(self, Self::NewTab(_))
+    }
+
+    /// The same destination once tabs are renumbered by `renumber`, or
+    /// `None` if its tab is gone.
+    pub fn renumber(self, renumber: impl Fn(usize) -> Option<usize>) -> Option<Self> {
+        Some(match self {
+            Self::Tab(i, visit) => Self::Tab(renumber(i)?, visit),
+            Self::NewTab(i) => Self::NewTab(renumber(i)?),
+            Self::Snapshots(i) => Self::Snapshots(renumber(i)?),
+        })
+    }
+}
+
+/// What a finished job produced
+pub enum Outcome {
+    Page(Loaded),
+    /// Wayback timestamps, oldest first
+    Snapshots(Vec<String>),
+}
+
+pub struct Loading {
+    pub url: String,
+    pub destination: Destination,
+    /// The latest report from the worker
+    pub progress: Option<Progress>,
+    /// The start of a text file still downloading
+    pub head: Option<String>,
+    updates: Receiver<Progress>,
+    result: Receiver<Result<Outcome>>,
+}
+
+impl Loading {
+    pub fn start(loader: Loader, url: &str, destination: Destination, options: ReadOptions) -> Self {
+        let (sink, updates) = mpsc::channel();
+        let (done, result) = mpsc::channel();
+        let job = url.to_string();
+        // Dropping the `Loading` cancels: the result has nowhere to go
+        thread::spawn(move || {
+            let outcome = progress::with_sink(sink, || match destination {
+                Destination::Snapshots(_) => loader.snapshots(&job).map(Outcome::Snapshots),
+                _ => loader.load(&job, options).map(Outcome::Page),
+            });
+            let _ = done.send(outcome);
+        });
+        Self { url: url.to_string(), destination, progress: None, head: None, updates, result }
+    }
+
+    /// Take in progress reports, and the result once there is one.
+    pub fn poll(&mut self) -> Option<Result<Outcome>> {
+        while let Ok(progress) = self.updates.try_recv() {
+            match progress {
+                Progress::Head { bytes, content_type } => {
+                    self.head = browser::partial_text(&self.url, &bytes, content_type.as_deref());
+                }
+                progress => self.progress = Some(progress),
+            }
+        }
+        match self.result.try_recv() {
+            Ok(loaded) => Some(loaded),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(anyhow!("The page loader stopped unexpectedly"))),
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Local directory trees, e.g. a mirror on a NAS
+//!
+//! `file://` URLs and plain paths are read from disk. Descriptions come
+//! from an `index.html` listing, `00_INDEX` or `FILES.BBS` if there is one.
+
+use crate::error::LoadError;
+use crate::fetcher::{Response, HEAD_LEN};
+use crate::parser::{self, DirEntry};
+use crate::source::{Metadata, ReadOptions, Source};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Description files, in order of preference
+const INDEX_FILES: &[&str] = &["index.html", "00_INDEX", "00-INDEX", "FILES.BBS"];
+
+/// Bytes left as they are in `file://` URLs, besides ASCII letters and
+/// digits. Not `!`, which would look like the way into an archive.
+const URL_SAFE: &[u8] = b"/-._~$&'()*+,;=:@";
+
+/// The path `url` refers to, if it's local.
+pub fn path_of(url: &str) -> Option<PathBuf> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return Some(PathBuf::from(decode(path)));
+    }
+    if url.contains("://") {
+        return None;
+    }
+    match url.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().map(|home| home.join(rest)),
+        None => Some(PathBuf::from(url)),
+    }
+}
+
+pub fn url_of(path: &Path, is_dir: bool) -> String {
+    let mut url = format!("file://{}", encode(&path.to_string_lossy()));
+    if is_dir && !url.ends_with('/') {
+        url.push('/');
+    }
+    url
+}
+
+/// A URL for `arg` from the command line: URLs as they are, paths as the
+/// `file://` URL of what they point at.
+pub fn url_for_arg(arg: &str) -> Result<String> {
+    if arg.contains("://") {
+        return Ok(arg.to_string());
+    }
+    let path = fs::canonicalize(arg).with_context(|| format!("Can't open {arg}"))?;
+    Ok(url_of(&path, path.is_dir()))
+}
+
+/// `%XX` escapes for everything but `URL_SAFE` characters
+fn encode(path: &str) -> String {
+    let mut url = String::with_capacity(path.len());
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || URL_SAFE.contains(&b) {
+            url.push(b as char);
+        } else {
+            url.push_str(&format!("%{b:02X}"));
+        }
+    }
+    url
+}
+
+/// Undo `%XX` escapes, leaving anything that isn't one as it is.
+pub fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 3)
+            .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// The file at `path`, or just its first `HEAD_LEN` bytes.
+pub fn read_file(path: &Path, head: bool) -> Result<Vec<u8>> {
+    let context = || format!("Can't read {}", path.display());
+    if !head {
+        return fs::read(path).with_context(context);
+    }
+    let mut bytes = Vec::new();
+    fs::File::open(path).and_then(|file| file.take(HEAD_LEN as u64).read_to_end(&mut bytes)).with_context(context)?;
+    Ok(bytes)
+}
+
+pub fn list_dir(path: &Path) -> Result<Vec<DirEntry>> {
+    let descriptions = descriptions(path);
+    let mut entries = Vec::new();
+    for item in fs::read_dir(path).with_context(|| format!("Can't list {}", path.display()))? {
+        let item = item?;
+        let name = item.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        // Follows symlinks, so linked directories browse like real ones
+        let Ok(meta) = fs::metadata(item.path()) else {
+            continue;
+        };
+        entries.push(DirEntry {
+            url: url_of(&item.path(), meta.is_dir()),
+            description: descriptions.get(&name.to_lowercase()).cloned().unwrap_or_default(),
+            size: meta.is_file().then_some(meta.len()),
+            is_dir: meta.is_dir(),
+            name,
+        });
+    }
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+    Ok(entries)
+}
+
+/// The filesystem as a `Source`
+#[derive(Default)]
+pub struct LocalSource {
+    /// Files bigger than this are only read when any size will do
+    max_download: Option<u64>,
+}
+
+impl LocalSource {
+    pub fn new(max_download: Option<u64>) -> Self {
+        Self { max_download }
+    }
+
+    fn path(url: &str) -> Result<PathBuf> {
+        path_of(url).with_context(|| format!("{url} isn't a local path"))
+    }
+}
+
+impl Source for LocalSource {
+    fn metadata(&self, url: &str) -> Result<Metadata> {
+        let path = Self::path(url)?;
+        let meta = fs::metadata(&path).with_context(|| format!("Can't open {}", path.display()))?;
+        Ok(Metadata { is_dir: meta.is_dir() })
+    }
+
+    fn list(&self, url: &str) -> Result<Vec<DirEntry>> {
+        list_dir(&Self::path(url)?)
+    }
+
+    fn read(&self, url: &str, options: ReadOptions) -> Result<Response> {
+        let path = Self::path(url)?;
+        // The same limit as downloads, so a huge file on a NAS asks first too
+        if let Some(limit) = self.max_download.filter(|_| !options.any_size && !options.head)
+            && let Ok(meta) = fs::metadata(&path)
+            && meta.len() > limit
+        {
+            return Err(LoadError::TooLarge { size: Some(meta.len()), limit }.into());
+        }
+        Ok(Response { bytes: read_file(&path, options.head)?, content_type: None, changed: false })
+    }
+}
+
+/// File name (lowercased) to description, from the first index file found.
+fn descriptions(dir: &Path) -> HashMap<String, String> {
+    for index in INDEX_FILES {
+        let Ok(bytes) = fs::read(dir.join(index)) else {
+            continue;
+        };
+        let text = String::from_utf8_lossy(&bytes);
+        return if index.ends_with(".html") {
+            parser::parse_file_listing(&text, "")
+                .into_iter()
+                .filter(|e| !e.description.is_empty())
+                .map(|e| (e.name.to_lowercase(), e.description))
+                .collect()
+        } else {
+            parse_index(&text)
+        };
+    }
+    HashMap::new()
+}
+
+/// `NAME  description` lines, where indented lines continue the
+/// description above. Covers both `00_INDEX` and BBS `FILES.BBS` lists.
+fn parse_index(text: &str) -> HashMap<String, String> {
+    let mut map: HashMap<String, String> = HashMap::new();
+    let mut current: Option<String> = None;
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) {
+            // FILES.BBS continuations are often marked with | or +
+            let more = line.trim().trim_start_matches(['|', '+']).trim();
+            if let Some(desc) = current.as_ref().and_then(|name| map.get_mut(name)) {
+                if !desc.is_empty() {
+                    desc.push(' ');
+                }
+                desc.push_str(more);
+            }
+            continue;
+        }
+        let (name, desc) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let name = name.to_lowercase();
+        map.insert(name.clone(), desc.trim().to_string());
+        current = Some(name);
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("textfiles-local-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn escapes_paths_in_urls() {
+        let path = Path::new("/srv/text files/100%/why?#1!/a+b.txt");
+        let url = url_of(path, false);
+        assert_eq!(url, "file:///srv/text%20files/100%25/why%3F%231%21/a+b.txt");
+        assert_eq!(path_of(&url).unwrap(), path);
+        assert_eq!(decode("50%2"), "50%2");
+        assert_eq!(decode("%zz%41"), "%zzA");
+        // Plain paths aren't URLs, so there's nothing to undo
+        assert_eq!(path_of("/srv/100%25").unwrap(), Path::new("/srv/100%25"));
+    }
+
+    #[test]
+    fn lists_and_reads_awkward_names() {
+        let dir = scratch("names");
+        fs::write(dir.join("read me.txt"), "spaces").unwrap();
+        fs::write(dir.join("100%.txt"), "percent").unwrap();
+        fs::write(dir.join("index.html"), "<html></html>").unwrap();
+
+        let url = url_for_arg(dir.to_str().unwrap()).unwrap();
+        assert!(url.starts_with("file://") && url.ends_with('/'));
+        assert!(LocalSource::default().metadata(&url).unwrap().is_dir);
+        let entries = LocalSource::default().list(&url).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["100%.txt", "index.html", "read me.txt"]);
+        for (entry, body) in [(&entries[0], "percent"), (&entries[2], "spaces")] {
+            assert_eq!(LocalSource::default().read(&entry.url, ReadOptions::default()).unwrap().bytes, body.as_bytes());
+        }
+        assert!(url_for_arg(dir.join("missing").to_str().unwrap()).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn asks_before_reading_big_files() {
+        let dir = scratch("big");
+        fs::write(dir.join("big.txt"), "x".repeat(HEAD_LEN * 2)).unwrap();
+        let url = url_of(&dir.join("big.txt"), false);
+        let source = LocalSource::new(Some(HEAD_LEN as u64));
+
+        let e = source.read(&url, ReadOptions::default()).err().unwrap();
+        assert!(matches!(
+            crate::error::classify(&e),
+            Some(LoadError::TooLarge { size: Some(size), limit }) if *size == 2 * *limit,
+        ));
+        let head = source.read(&url, ReadOptions { head: true, ..ReadOptions::default() }).unwrap();
+        assert_eq!(head.bytes.len(), HEAD_LEN);
+        let whole = source.read(&url, ReadOptions { any_size: true, ..ReadOptions::default() }).unwrap();
+        assert_eq!(whole.bytes.len(), HEAD_LEN * 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,31 +1,92 @@
 //! TEXTFILES.COM Browser
 
+mod archive;
+mod binary;
 mod browser;
+mod cache;
+mod config;
+mod error;
 mod fetcher;
+mod governor;
+#[cfg(test)]
+mod harness;
+mod html;
+mod keymap;
+mod links;
+mod loading;
+mod local;
+mod mirror;
 mod parser;
+mod preview;
+mod progress;
+mod reflow;
+mod robots;
+mod source;
+mod tab;
+mod theme;
 mod ui;
+mod wayback;
 
 use anyhow::Result;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyModifiers},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{backend::CrosstermBackend, Terminal};
-use std::{io, time::Duration};
+use ratatui::{backend::CrosstermBackend, buffer::Buffer, Terminal};
+use std::{env, io, time::Duration};
+
+const USAGE: &str = "\
+Usage: textfiles-browser [OPTIONS] [PATH | URL]
+
+Opens PATH (a directory or file) or URL instead of the home page.
+
+Options:
+  --print-config  Print the effective configuration and exit
+  -h, --help      Print this help and exit";
 
 fn main() -> Result<()> {
+    // Before the config, so a broken config file doesn't get in the way of help
+    let arg = env::args().nth(1);
+    match arg.as_deref() {
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            return Ok(());
+        }
+        Some(arg) if arg.starts_with('-') && arg != "--print-config" => {
+            anyhow::bail!("Unknown option: {arg}\n\n{USAGE}")
+        }
+        _ => {}
+    }
+
+    let mut config = config::Config::load()?;
+
+    match arg.as_deref() {
+        Some("--print-config") => {
+            match config::Config::path() {
+                Some(path) if path.exists() => println!("# Loaded from {}", path.display()),
+                Some(path) => println!("# Defaults ({} not found)", path.display()),
+                None => println!("# Defaults (no config directory)"),
+            }
+            print!("{}", config.to_toml()?);
+            return Ok(());
+        }
+        Some(arg) => config.home_url = local::url_for_arg(arg)?,
+        None => {}
+    }
+
+    let mut app = ui::App::new(&config)?;
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = ui::App::new();
     let res = run(&mut terminal, &mut app);
 
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
     terminal.show_cursor()?;
 
     if let Err(e) = res {
@@ -36,53 +97,27 @@ fn main() -> Result<()> {
 
 fn run<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut ui::App) -> Result<()> {
     app.load_home()?;
+    let mut drawn: Option<Buffer> = None;
 
     loop {
         let size = terminal.size()?;
-        let visible_height = size.height.saturating_sub(16) as usize; // header(12) + status(2) + borders(2)
         app.update_view_width(size.width);
 
-        terminal.draw(|f| ui::draw(f, app))?;
+        let frame = terminal.draw(|f| ui::draw(f, app))?;
+        // The links stay painted until a redraw writes over them
+        if drawn.as_ref() != Some(frame.buffer) {
+            drawn = Some(frame.buffer.clone());
+            ui::write_hyperlinks(&mut io::stdout(), &app.hyperlinks)?;
+        }
 
         if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key) = event::read()? {
-                // Dismiss error
-                if app.error.is_some() {
-                    app.error = None;
-                    continue;
-                }
-
-                // Quit
-                if key.code == KeyCode::Char('q')
-                    || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
-                {
-                    return Ok(());
-                }
-
-                match app.mode {
-                    ui::Mode::Browser => match key.code {
-                        KeyCode::Up | KeyCode::Char('k') => app.previous(),
-                        KeyCode::Down | KeyCode::Char('j') => app.next(),
-                        KeyCode::PageUp => app.page_up(),
-                        KeyCode::PageDown => app.page_down(),
-                        KeyCode::Home | KeyCode::Char('g') => app.home(),
-                        KeyCode::End | KeyCode::Char('G') => app.end(),
-                        KeyCode::Enter => { app.select()?; }
-                        KeyCode::Backspace | KeyCode::Left | KeyCode::Esc => { app.go_back()?; }
-                        KeyCode::Char('r') => { app.refresh()?; }
-                        _ => {}
-                    },
-                    ui::Mode::Viewer => match key.code {
-                        KeyCode::Up | KeyCode::Char('k') => app.scroll_up(1),
-                        KeyCode::Down | KeyCode::Char('j') => app.scroll_down(1, visible_height),
-                        KeyCode::PageUp | KeyCode::Char('b') => app.scroll_up(20),
-                        KeyCode::PageDown | KeyCode::Char(' ') => app.scroll_down(20, visible_height),
-                        KeyCode::Home | KeyCode::Char('g') => app.scroll_home(),
-                        KeyCode::End | KeyCode::Char('G') => app.scroll_end(visible_height),
-                        KeyCode::Backspace | KeyCode::Left | KeyCode::Esc | KeyCode::Char('q') => { app.go_back()?; }
-                        _ => {}
-                    },
-                }
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => app.handle_key(key)?,
+                Event::Mouse(mouse) => app.handle_mouse(mouse)?,
+                _ => {}
+            }
+            if app.quit {
+                return Ok(());
             }
         }
         app.tick();
@@ -0,0 +1,313 @@
+//! Copies of the site to fall back on
+//!
+//! URLs under the site's own root are what history and bookmarks keep.
+//! Reads of them go to the first root that's up, the site itself before
+//! its mirrors, and whatever comes back is described in the site's URLs
+//! again. A root that fails is passed over until `recheck_secs` have gone
+//! by, after which the next read tries it again.
+//!
+//! Before a page is loaded, a root that hasn't been heard from within
+//! `recheck_secs` is probed: local roots by looking for the directory,
+//! others by reading the start of their front page. The page is then
+//! described by the first root that's up, so a directory on a local
+//! mirror is listed rather than read.
+
+use crate::config::MirrorConfig;
+use crate::error;
+use crate::fetcher::Response;
+use crate::local;
+use crate::parser::DirEntry;
+use crate::progress::{self, Progress};
+use crate::source::{Metadata, ReadOptions, Source, Sources};
+use anyhow::{anyhow, Result};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub struct Mirrors {
+    /// The site's root, then its mirrors', each ending in `/`
+    roots: Vec<String>,
+    /// Where the roots are read from
+    sources: Sources,
+    /// Per root, what the last request or probe found
+    health: Mutex<Vec<Option<Health>>>,
+    recheck: Duration,
+}
+
+#[derive(Clone, Copy)]
+struct Health {
+    up: bool,
+    at: Instant,
+}
+
+impl Mirrors {
+    pub fn new(config: &MirrorConfig, sources: Sources) -> Self {
+        // Local roots as the URLs their listings use
+        let roots: Vec<String> = std::iter::once(&config.site)
+            .chain(&config.urls)
+            .map(|root| match local::path_of(root) {
+                Some(path) => local::url_of(&path, true),
+                None => format!("{}/", root.trim_end_matches('/')),
+            })
+            .collect();
+        Self {
+            health: Mutex::new(vec![None; roots.len()]),
+            roots,
+            sources,
+            recheck: Duration::from_secs(config.recheck_secs),
+        }
+    }
+
+    /// `url` under the site's root, if it's under any root.
+    pub fn canonical(&self, url: &str) -> String {
+        match self.split(url) {
+            Some(path) => format!("{}{path}", self.roots[0]),
+            None => url.to_string(),
+        }
+    }
+
+    /// The part of `url` after whichever root it's under.
+    fn split<'a>(&self, url: &'a str) -> Option<&'a str> {
+        self.roots.iter().find_map(|root| {
+            url.strip_prefix(root.as_str()).or_else(|| (root.strip_suffix('/') == Some(url)).then_some(""))
+        })
+    }
+
+    /// What's known about `root`, if it's recent enough to go by.
+    fn health(&self, root: usize) -> Option<bool> {
+        let health = self.health.lock().unwrap_or_else(|e| e.into_inner());
+        health[root].filter(|h| h.at.elapsed() < self.recheck).map(|h| h.up)
+    }
+
+    /// Roots to try, in config order but with those known to be down last.
+    fn order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.roots.len()).collect();
+        order.sort_by_key(|&i| self.health(i) == Some(false));
+        order
+    }
+
+    fn mark(&self, root: usize, up: bool) {
+        let mut health = self.health.lock().unwrap_or_else(|e| e.into_inner());
+        health[root] = Some(Health { up, at: Instant::now() });
+    }
+
+    /// Whether `root` is up, asking it if nothing recent is known.
+    fn probe(&self, root: usize) -> bool {
+        if let Some(up) = self.health(root) {
+            return up;
+        }
+        let url = &self.roots[root];
+        let up = match self.sources.get(url).and_then(|source| source.metadata(url)) {
+            // Only local roots can tell without a request
+            Ok(meta) if meta.is_dir => true,
+            Ok(_) => {
+                let sources = self.sources.with_options(ReadOptions { head: true, ..ReadOptions::default() });
+                // Its progress isn't the page's
+                !progress::muted(|| sources.read(url)).is_err_and(|e| error::is_outage(&e))
+            }
+            Err(_) => false,
+        };
+        self.mark(root, up);
+        up
+    }
+
+    /// `url` under the first root with a cached copy of it.
+    fn cached_copy(&self, url: &str) -> Option<String> {
+        let Some(path) = self.split(url) else {
+            return self.sources.is_cached(url).then(|| url.to_string());
+        };
+        self.roots.iter().map(|root| format!("{root}{path}")).find(|url| self.sources.is_cached(url))
+    }
+
+    /// `read` `url` from each root in turn until one answers, or fails in
+    /// a way another copy wouldn't fix.
+    fn failover<T>(&self, url: &str, read: impl Fn(&str) -> Result<T>) -> Result<T> {
+        let Some(path) = self.split(url) else {
+            return read(url);
+        };
+        let order = self.order();
+        let mut last = None;
+        for (n, &root) in order.iter().enumerate() {
+            match read(&format!("{}{path}", self.roots[root])) {
+                // Mirrors may lag behind the site, so a 404 is believed
+                Err(e) if error::is_outage(&e) => {
+                    self.mark(root, false);
+                    if let Some(&next) = order.get(n + 1) {
+                        progress::report(Progress::Failover { reason: e.to_string(), to: host(&self.roots[next]).to_string() });
+                    }
+                    last = Some(e);
+                }
+                result => {
+                    self.mark(root, true);
+                    return result;
+                }
+            }
+        }
+        Err(last.unwrap_or_else(|| anyhow!("No mirrors configured")))
+    }
+}
+
+fn host(root: &str) -> &str {
+    let rest = root.split_once("://").map_or(root, |(_, rest)| rest);
+    rest.split('/').find(|part| !part.is_empty()).unwrap_or(rest)
+}
+
+impl Source for Mirrors {
+    fn metadata(&self, url: &str) -> Result<Metadata> {
+        let Some(path) = self.split(url) else {
+            return self.sources.get(url)?.metadata(url);
+        };
+        // The root a read would go to, which may list directories itself
+        match self.order().into_iter().find(|&root| self.probe(root)) {
+            Some(root) => {
+                let url = format!("{}{path}", self.roots[root]);
+                self.sources.get(&url)?.metadata(&url)
+            }
+            // Leave it to the read to say what's wrong
+            None => Ok(Metadata::default()),
+        }
+    }
+
+    fn list(&self, url: &str) -> Result<Vec<DirEntry>> {
+        let mut entries = self.failover(url, |url| self.sources.get(url)?.list(url))?;
+        for entry in &mut entries {
+            entry.url = self.canonical(&entry.url);
+        }
+        Ok(entries)
+    }
+
+    fn read(&self, url: &str, options: ReadOptions) -> Result<Response> {
+        // A cached copy is kept under the root it came from, whether or not
+        // that root is up now
+        if options.cached && let Some(url) = self.cached_copy(url) {
+            return self.sources.get(&url)?.read(&url, options);
+        }
+        self.failover(url, |url| self.sources.get(url)?.read(url, options))
+    }
+
+    fn is_cached(&self, url: &str) -> bool {
+        self.cached_copy(url).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::LoadError;
+    use crate::local::LocalSource;
+    use crate::source::MemorySource;
+    use std::fs;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Files from a `MemorySource`, except that one host is unreachable.
+    /// Like the fetcher, it can't tell directories from files.
+    struct Flaky {
+        files: MemorySource,
+        down: &'static str,
+        attempts: AtomicUsize,
+    }
+
+    impl Source for Flaky {
+        fn metadata(&self, _url: &str) -> Result<Metadata> {
+            Ok(Metadata::default())
+        }
+
+        fn list(&self, url: &str) -> Result<Vec<DirEntry>> {
+            self.files.list(url)
+        }
+
+        fn read(&self, url: &str, options: ReadOptions) -> Result<Response> {
+            if url.contains(self.down) {
+                self.attempts.fetch_add(1, Ordering::SeqCst);
+                return Err(LoadError::Offline(self.down.to_string()).into());
+            }
+            self.files.read(url, options)
+        }
+    }
+
+    fn setup(recheck_secs: u64) -> (Mirrors, Arc<Flaky>) {
+        with_mirror("http://mirror.example/textfiles/", "textfiles.com", recheck_secs)
+    }
+
+    fn with_mirror(mirror: &str, down: &'static str, recheck_secs: u64) -> (Mirrors, Arc<Flaky>) {
+        let flaky = Arc::new(Flaky {
+            files: MemorySource::new()
+                .with("http://mirror.example/textfiles/hacking/phreak.txt", "BLUE BOXES", None)
+                .with("http://mirror.example/textfiles/hacking/unix.txt", "UNIX", None),
+            down,
+            attempts: AtomicUsize::new(0),
+        });
+        let mut sources = Sources::default();
+        sources.register("http", flaky.clone());
+        sources.register("file", Arc::new(LocalSource::default()));
+        let config = MirrorConfig {
+            site: "http://textfiles.com".to_string(),
+            urls: vec![mirror.to_string()],
+            recheck_secs,
+        };
+        (Mirrors::new(&config, sources), flaky)
+    }
+
+    #[test]
+    fn fails_over_and_keeps_urls_canonical() {
+        let (mirrors, _) = setup(300);
+        let response = mirrors.read("http://textfiles.com/hacking/phreak.txt", ReadOptions::default()).unwrap();
+        assert_eq!(response.bytes, b"BLUE BOXES");
+        let entries = mirrors.list("http://textfiles.com/hacking/").unwrap();
+        assert_eq!(entries[0].url, "http://textfiles.com/hacking/phreak.txt");
+        assert_eq!(mirrors.canonical("http://mirror.example/textfiles/humor/"), "http://textfiles.com/humor/");
+        assert_eq!(mirrors.canonical("http://mirror.example/textfiles"), "http://textfiles.com/");
+        assert_eq!(mirrors.canonical("http://example.com/x"), "http://example.com/x");
+    }
+
+    #[test]
+    fn passes_over_a_failed_root_until_recheck() {
+        let (mirrors, flaky) = setup(300);
+        mirrors.read("http://textfiles.com/hacking/phreak.txt", ReadOptions::default()).unwrap();
+        mirrors.read("http://textfiles.com/hacking/unix.txt", ReadOptions::default()).unwrap();
+        assert_eq!(flaky.attempts.load(Ordering::SeqCst), 1);
+
+        let (mirrors, flaky) = setup(0);
+        mirrors.read("http://textfiles.com/hacking/phreak.txt", ReadOptions::default()).unwrap();
+        mirrors.read("http://textfiles.com/hacking/unix.txt", ReadOptions::default()).unwrap();
+        assert_eq!(flaky.attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn believes_missing_pages() {
+        let (mirrors, _) = setup(300);
+        let e = mirrors.read("http://mirror.example/textfiles/gone.txt", ReadOptions::default()).err().unwrap();
+        assert_eq!(error::classify(&e), Some(&LoadError::Status(404)));
+    }
+
+    #[test]
+    fn probes_before_describing_pages() {
+        let (mirrors, _) = with_mirror("http://mirror.example/textfiles/", "nowhere", 300);
+        assert!(!mirrors.metadata("http://textfiles.com/hacking/").unwrap().is_dir);
+        assert_eq!(mirrors.health(0), Some(true));
+
+        let (mirrors, flaky) = setup(300);
+        mirrors.metadata("http://textfiles.com/hacking/phreak.txt").unwrap();
+        mirrors.metadata("http://textfiles.com/hacking/unix.txt").unwrap();
+        assert_eq!((mirrors.health(0), mirrors.health(1)), (Some(false), Some(true)));
+        // Once down, the site isn't asked again until the recheck
+        assert_eq!(flaky.attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn lists_directories_on_local_mirrors() {
+        let dir = std::env::temp_dir().join(format!("textfiles-mirror-{}", std::process::id()));
+        fs::create_dir_all(dir.join("hacking")).unwrap();
+        fs::write(dir.join("hacking/phreak.txt"), "BLUE BOXES").unwrap();
+        let (mirrors, flaky) = with_mirror(dir.to_str().unwrap(), "textfiles.com", 300);
+
+        assert!(mirrors.metadata("http://textfiles.com/hacking/").unwrap().is_dir);
+        let entries = mirrors.list("http://textfiles.com/hacking/").unwrap();
+        assert_eq!(entries[0].url, "http://textfiles.com/hacking/phreak.txt");
+        let response = mirrors.read(&entries[0].url, ReadOptions::default()).unwrap();
+        assert_eq!(response.bytes, b"BLUE BOXES");
+        // The probe found the site down, so nothing else went there
+        assert_eq!(flaky.attempts.load(Ordering::SeqCst), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
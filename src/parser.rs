@@ -7,10 +7,12 @@ pub struct DirEntry {
     pub name: String,
     pub url: String,
     pub description: String,
+    /// In bytes, where the listing gives it
+    pub size: Option<u64>,
     pub is_dir: bool,
 }
 
-pub fn parse_directory_html(html: &str) -> Vec<DirEntry> {
+pub fn parse_directory_html(html: &str, base_url: &str) -> Vec<DirEntry> {
     // directory.html has: <B><A HREF="dirname">Display Name</A></B><BR><I>Description</I>
     let document = Html::parse_document(html);
     let td_selector = Selector::parse("td").unwrap();
@@ -18,6 +20,7 @@ pub fn parse_directory_html(html: &str) -> Vec<DirEntry> {
     let i_selector = Selector::parse("i").unwrap();
 
     let mut entries = Vec::new();
+    let base = &base_url[..base_url.rfind('/').map_or(0, |i| i + 1)];
 
     for td in document.select(&td_selector) {
         // Find link in this TD
@@ -47,12 +50,13 @@ pub fn parse_directory_html(html: &str) -> Vec<DirEntry> {
             .map(|i| i.text().collect::<String>().trim().to_string())
             .unwrap_or_default();
 
-        let url = format!("http://textfiles.com/{}/", href);
+        let url = format!("{}{}/", base, href);
 
         entries.push(DirEntry {
             name,
             url,
             description,
+            size: None,
             is_dir: true,
         });
     }
@@ -104,6 +108,13 @@ pub fn parse_file_listing(html: &str, base_url: &str) -> Vec<DirEntry> {
             String::new()
         };
 
+        // Size is in the middle TD, when there is one
+        let size = if tds.len() >= 3 {
+            tds[1].text().collect::<String>().trim().parse().ok()
+        } else {
+            None
+        };
+
         let url = format!("{}/{}", base, href);
         let is_dir = href.ends_with('/');
 
@@ -111,6 +122,7 @@ pub fn parse_file_listing(html: &str, base_url: &str) -> Vec<DirEntry> {
             name: name.trim_end_matches('/').to_string(),
             url,
             description,
+            size,
             is_dir,
         });
     }
@@ -118,6 +130,28 @@ pub fn parse_file_listing(html: &str, base_url: &str) -> Vec<DirEntry> {
     entries
 }
 
+/// Whether `entries`, parsed from `html` as a file listing, make up most
+/// of the page. Articles with a table of links somewhere are documents.
+pub fn looks_like_listing(html: &str, entries: &[DirEntry]) -> bool {
+    if entries.len() < 2 {
+        return false;
+    }
+    let document = Html::parse_document(html);
+    let body_selector = Selector::parse("body").unwrap();
+    let tr_selector = Selector::parse("tr").unwrap();
+    let td_a_selector = Selector::parse("td:first-child a[href]").unwrap();
+    let words = |el: ElementRef| el.text().flat_map(str::split_whitespace).count();
+
+    let body_words = document.select(&body_selector).next().map(words).unwrap_or(0);
+    // Innermost rows only, so a layout table around the page doesn't count
+    let listing_words: usize = document.select(&tr_selector)
+        .filter(|tr| tr.select(&tr_selector).all(|inner| inner == *tr))
+        .filter(|tr| tr.select(&td_a_selector).next().is_some())
+        .map(words)
+        .sum();
+    listing_words * 5 >= body_words * 2
+}
+
 pub fn parse_page_title(html: &str) -> Option<String> {
     let document = Html::parse_document(html);
     let title_selector = Selector::parse("title").ok()?;
@@ -126,3 +160,106 @@ pub fn parse_page_title(html: &str) -> Option<String> {
         .next()
         .map(|t| t.text().collect::<String>().trim().to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIRECTORY: &str = include_str!("fixtures/directory.html");
+    const HACKING: &str = include_str!("fixtures/hacking.html");
+    const ARTICLE: &str = include_str!("fixtures/article.html");
+
+    /// (name, url, description, size, is_dir) for compact comparisons
+    fn summary(entries: &[DirEntry]) -> Vec<(&str, &str, &str, Option<u64>, bool)> {
+        entries.iter()
+            .map(|e| (e.name.as_str(), e.url.as_str(), e.description.as_str(), e.size, e.is_dir))
+            .collect()
+    }
+
+    #[test]
+    fn directory_page() {
+        let entries = parse_directory_html(DIRECTORY, "http://textfiles.com/directory.html");
+        // Links with dots, slashes or over-long names aren't categories
+        assert_eq!(summary(&entries), [
+            ("Ye Olde Top 100", "http://textfiles.com/100/",
+             "The most widely spread files of the BBS world, before it all went online", None, true),
+            ("ADVENTURE", "http://textfiles.com/adventure/", "Walkthroughs and Solutions to Text Adventures", None, true),
+            ("ANARCHY", "http://textfiles.com/anarchy/", "Mayhem, Destruction and Weapons", None, true),
+            ("APPLE II", "http://textfiles.com/apple/", "Files from the Apple II scene, 1979-1992", None, true),
+            ("BBS", "http://textfiles.com/bbs/", "Bulletin Board Systems: History, Culture and Rules", None, true),
+            ("COMPUTERS", "http://textfiles.com/computers/", "", None, true),
+            ("HACKING", "http://textfiles.com/hacking/", "Breaking into Computers and Networks", None, true),
+        ]);
+    }
+
+    #[test]
+    fn file_listing() {
+        let entries = parse_file_listing(HACKING, "http://textfiles.com/hacking/");
+        // No header, parent, sort or navigation rows
+        assert_eq!(summary(&entries), [
+            ("INTERNET", "http://textfiles.com/hacking/INTERNET/",
+             "Files about the Internet, before it was a household word", None, true),
+            ("MICROSOFT", "http://textfiles.com/hacking/MICROSOFT/",
+             "Microsoft products and their many, many holes", None, true),
+            ("14fun.txt", "http://textfiles.com/hacking/14fun.txt",
+             "Fun With Automated Answering Machines, by The Rat", Some(3876), false),
+            ("ACCTNAME.HAC", "http://textfiles.com/hacking/ACCTNAME.HAC",
+             "A List of Default Account Names on Various Systems", Some(1043), false),
+            ("ALTOS.HAC", "http://textfiles.com/hacking/ALTOS.HAC", "Hacking the Altos 586 & 986 Systems", Some(7213), false),
+            ("bbs.pw", "http://textfiles.com/hacking/bbs.pw", "Common BBS Sysop Passwords", Some(1204), false),
+            ("novell.faq", "http://textfiles.com/hacking/novell.faq", "", Some(81462), false),
+        ]);
+        assert!(looks_like_listing(HACKING, &entries));
+        assert_eq!(parse_page_title(HACKING).as_deref(), Some("T E X T F I L E S: Hacking"));
+    }
+
+    /// The fixtures are written by hand, so check the parser against the
+    /// live site too: `cargo test -- --ignored` with network access. With
+    /// `UPDATE_FIXTURES=1` the pages are also saved to `src/fixtures/live`,
+    /// to be trimmed and checked in as fixtures.
+    #[test]
+    #[ignore]
+    fn live_pages_still_parse() {
+        use crate::config::NetworkConfig;
+        use crate::fetcher::Fetcher;
+        use crate::source::{ReadOptions, Source};
+        use std::{env, fs, path::Path};
+
+        let fetcher = Fetcher::new(&NetworkConfig::default(), None).unwrap();
+        let save = env::var_os("UPDATE_FIXTURES").is_some();
+        let page = |url: &str, name: &str| {
+            let bytes = fetcher.read(url, ReadOptions::default()).unwrap().bytes;
+            if save {
+                let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/fixtures/live");
+                fs::create_dir_all(&dir).unwrap();
+                fs::write(dir.join(name), &bytes).unwrap();
+            }
+            String::from_utf8_lossy(&bytes).into_owned()
+        };
+
+        let directory = page("http://textfiles.com/directory.html", "directory.html");
+        let categories = parse_directory_html(&directory, "http://textfiles.com/directory.html");
+        assert!(categories.len() > 20, "{} categories", categories.len());
+        assert!(categories.iter().any(|e| e.url == "http://textfiles.com/hacking/" && !e.description.is_empty()));
+
+        let hacking = page("http://textfiles.com/hacking/", "hacking.html");
+        let entries = parse_file_listing(&hacking, "http://textfiles.com/hacking/");
+        assert!(looks_like_listing(&hacking, &entries));
+        assert!(entries.iter().any(|e| e.is_dir));
+        assert!(entries.iter().filter(|e| e.size.is_some() && !e.description.is_empty()).count() > 50);
+
+        for (url, name) in [("http://textfiles.com/100/", "100.html"), ("http://textfiles.com/humor/", "humor.html")] {
+            let listing = page(url, name);
+            let entries = parse_file_listing(&listing, url);
+            assert!(looks_like_listing(&listing, &entries), "{url}");
+            assert!(entries.iter().any(|e| e.size.is_some()), "{url}");
+        }
+    }
+
+    #[test]
+    fn article_is_not_a_listing() {
+        let entries = parse_file_listing(ARTICLE, "http://textfiles.com/bbs/");
+        assert!(entries.len() >= 2);
+        assert!(!looks_like_listing(ARTICLE, &entries));
+    }
+}
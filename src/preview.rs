@@ -0,0 +1,164 @@
+//! Background preview of the highlighted directory entry
+//!
+//! Only the start of each file is read, and only the last few previews
+//! are kept. Failures are shown while their entry stays highlighted and
+//! tried again the next time it is.
+
+use crate::browser::{self, Page};
+use crate::source::{ReadOptions, Sources};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// Ticks the selection must rest on an entry before it is fetched (~300ms)
+const DEBOUNCE_TICKS: u64 = 6;
+
+/// Previews kept for moving back and forth over a listing
+const CACHE_LEN: usize = 16;
+
+pub enum PreviewState {
+    Loading,
+    Ready(Page),
+    Failed(String),
+}
+
+pub struct Preview {
+    pub enabled: bool,
+    requests: Sender<String>,
+    results: Receiver<(String, Result<Page, String>)>,
+    /// Least recently used first
+    cache: Vec<(String, PreviewState)>,
+    wanted: Option<(String, u64)>,
+}
+
+impl Preview {
+    pub fn new(sources: Sources, enabled: bool) -> Self {
+        let (requests, jobs) = mpsc::channel::<String>();
+        let (done, results) = mpsc::channel();
+        let sources = sources.with_options(ReadOptions { head: true, ..ReadOptions::default() });
+        thread::spawn(move || {
+            for url in jobs {
+                let page = browser::load_page(&sources, &url).map_err(|e| e.to_string());
+                if done.send((url, page)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { enabled, requests, results, cache: Vec::new(), wanted: None }
+    }
+
+    /// Note that `url` is highlighted as of `tick`.
+    pub fn want(&mut self, url: &str, tick: u64) {
+        if self.wanted.as_ref().is_none_or(|(u, _)| u != url) {
+            self.wanted = Some((url.to_string(), tick));
+            self.cache.retain(|(_, state)| !matches!(state, PreviewState::Failed(_)));
+        }
+    }
+
+    /// Collect finished fetches and start the wanted one once it has
+    /// stayed selected long enough.
+    pub fn tick(&mut self, tick: u64) {
+        while let Ok((url, page)) = self.results.try_recv() {
+            match page {
+                Ok(page) => self.insert(url, PreviewState::Ready(page)),
+                // Only worth keeping while its entry is highlighted
+                Err(e) if self.wanted.as_ref().is_some_and(|(u, _)| *u == url) => {
+                    self.insert(url, PreviewState::Failed(e));
+                }
+                Err(_) => self.cache.retain(|(u, _)| *u != url),
+            }
+        }
+
+        if !self.enabled {
+            return;
+        }
+        let Some((url, since)) = &self.wanted else {
+            return;
+        };
+        if let Some(i) = self.cache.iter().position(|(u, _)| u == url) {
+            let entry = self.cache.remove(i);
+            self.cache.push(entry);
+        } else if tick.saturating_sub(*since) >= DEBOUNCE_TICKS && self.requests.send(url.clone()).is_ok() {
+            let url = url.clone();
+            self.insert(url, PreviewState::Loading);
+        }
+    }
+
+    pub fn get(&self, url: &str) -> Option<&PreviewState> {
+        self.cache.iter().find(|(u, _)| u == url).map(|(_, state)| state)
+    }
+
+    /// Keep `state` for `url`, dropping the least recently used preview
+    /// if there are too many.
+    fn insert(&mut self, url: String, state: PreviewState) {
+        self.cache.retain(|(u, _)| *u != url);
+        self.cache.push((url, state));
+        if self.cache.len() > CACHE_LEN {
+            self.cache.remove(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browser::Content;
+    use crate::fetcher::HEAD_LEN;
+    use crate::source::MemorySource;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Highlight `url` and wait for its preview.
+    fn show<'a>(preview: &'a mut Preview, url: &str, tick: &mut u64) -> &'a PreviewState {
+        preview.want(url, *tick);
+        loop {
+            *tick += 1;
+            preview.tick(*tick);
+            if !matches!(preview.get(url), None | Some(PreviewState::Loading)) {
+                return preview.get(url).unwrap();
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn preview() -> Preview {
+        let mut files = MemorySource::new().with("http://textfiles.com/big.txt", "A".repeat(HEAD_LEN * 4), Some("text/plain"));
+        for i in 0..=CACHE_LEN {
+            files.insert(&format!("http://textfiles.com/{i}.txt"), "text", Some("text/plain"));
+        }
+        let mut sources = Sources::default();
+        sources.register("http", Arc::new(files));
+        Preview::new(sources, true)
+    }
+
+    #[test]
+    fn reads_only_the_start() {
+        let mut preview = preview();
+        let state = show(&mut preview, "http://textfiles.com/big.txt", &mut 0);
+        let PreviewState::Ready(Page { content: Content::TextFile(text), .. }) = state else {
+            panic!("not previewed as text");
+        };
+        assert_eq!(text.len(), HEAD_LEN);
+    }
+
+    #[test]
+    fn keeps_only_recent_previews() {
+        let mut preview = preview();
+        let mut tick = 0;
+        for i in 0..=CACHE_LEN {
+            show(&mut preview, &format!("http://textfiles.com/{i}.txt"), &mut tick);
+        }
+        assert!(preview.get("http://textfiles.com/0.txt").is_none());
+        assert!(preview.get("http://textfiles.com/1.txt").is_some());
+        assert_eq!(preview.cache.len(), CACHE_LEN);
+    }
+
+    #[test]
+    fn forgets_failures_once_moved_past() {
+        let mut preview = preview();
+        let mut tick = 0;
+        let gone = "http://textfiles.com/gone.txt";
+        assert!(matches!(show(&mut preview, gone, &mut tick), PreviewState::Failed(_)));
+        show(&mut preview, "http://textfiles.com/0.txt", &mut tick);
+        assert!(preview.get(gone).is_none());
+    }
+}
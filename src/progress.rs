@@ -0,0 +1,50 @@
+//! Progress of the page load running on the current thread
+//!
+//! The loader thread installs a sink with [`with_sink`]; code deep in a
+//! source reports through [`report`] without threading a handle through
+//! every call. Reports from threads without a sink, like the preview
+//! worker, go nowhere.
+
+use std::cell::RefCell;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Progress {
+    /// Attempt `attempt` of `of` failed with `reason`; trying again after `wait`
+    Retrying { attempt: u32, of: u32, wait: Duration, reason: String },
+    /// `received` bytes of the body so far, out of `total` if known
+    Downloading { received: u64, total: Option<u64> },
+    /// The start of a body that's still arriving
+    Head { bytes: Vec<u8>, content_type: Option<String> },
+    /// A copy of the site failed with `reason`; trying the mirror at `to`
+    Failover { reason: String, to: String },
+}
+
+thread_local! {
+    static SINK: RefCell<Option<Sender<Progress>>> = const { RefCell::new(None) };
+}
+
+/// Run `f`, sending whatever it reports to `sink`.
+pub fn with_sink<T>(sink: Sender<Progress>, f: impl FnOnce() -> T) -> T {
+    SINK.with(|s| *s.borrow_mut() = Some(sink));
+    let result = f();
+    SINK.with(|s| *s.borrow_mut() = None);
+    result
+}
+
+/// Run `f` with its reports going nowhere.
+pub fn muted<T>(f: impl FnOnce() -> T) -> T {
+    let sink = SINK.with(|s| s.borrow_mut().take());
+    let result = f();
+    SINK.with(|s| *s.borrow_mut() = sink);
+    result
+}
+
+pub fn report(progress: Progress) {
+    SINK.with(|s| {
+        if let Some(sink) = s.borrow().as_ref() {
+            let _ = sink.send(progress);
+        }
+    });
+}
@@ -0,0 +1,360 @@
+//! Paragraph reflow for hard-wrapped text files
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WrapMode {
+    /// Chop lines at the view width, keeping the file's own line breaks
+    Hard,
+    /// Re-wrap prose paragraphs to the view width
+    Reflow,
+}
+
+/// A run of source lines after classification.
+enum Block {
+    /// Kept exactly as written (headers, tables, ASCII art, blank lines)
+    Verbatim(String),
+    /// Prose that may be re-wrapped to any width
+    Paragraph { first_indent: usize, indent: usize, words: Vec<String> },
+}
+
+/// Hard-wrap a single line at `width` characters.
+pub fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    if line.is_empty() {
+        return vec![String::new()];
+    }
+    let chars: Vec<char> = line.chars().collect();
+    if chars.len() <= width {
+        return vec![line.to_string()];
+    }
+    chars.chunks(width)
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
+
+/// Re-wrap prose paragraphs to `width`, leaving everything else in place.
+pub fn reflow(lines: &[String], width: usize) -> Vec<String> {
+    let source_width = source_width(lines);
+    let mut out = Vec::new();
+    for block in classify(lines, source_width) {
+        match block {
+            Block::Verbatim(line) => out.extend(wrap_line(&line, width)),
+            Block::Paragraph { first_indent, indent, words } => {
+                out.extend(fill(&words, first_indent, indent, width));
+            }
+        }
+    }
+    out
+}
+
+/// Estimate the column the file was originally wrapped at.
+fn source_width(lines: &[String]) -> usize {
+    let mut lens: Vec<usize> = lines.iter()
+        .map(|l| l.trim_end().chars().count())
+        .filter(|&n| n > 0)
+        .collect();
+    if lens.is_empty() {
+        return 80;
+    }
+    lens.sort_unstable();
+    lens[lens.len() * 9 / 10].clamp(40, 132)
+}
+
+/// A paragraph being collected from consecutive prose lines.
+struct Pending {
+    first_indent: usize,
+    indent: usize,
+    lines: usize,
+    words: Vec<String>,
+}
+
+impl Pending {
+    /// Whether a line indented by `lead` continues this paragraph.
+    fn continues(&self, lead: usize) -> bool {
+        if self.lines == 1 {
+            lead <= self.first_indent || lead == self.indent
+        } else {
+            lead == self.indent
+        }
+    }
+}
+
+fn classify(lines: &[String], source_width: usize) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut para: Option<Pending> = None;
+    let mut prev_short = true;
+
+    let flush = |para: &mut Option<Pending>, blocks: &mut Vec<Block>| {
+        if let Some(p) = para.take() {
+            blocks.push(Block::Paragraph { first_indent: p.first_indent, indent: p.indent, words: p.words });
+        }
+    };
+
+    for line in lines {
+        let line = line.trim_end();
+        let text = line.trim_start();
+        let lead = line.chars().count() - text.chars().count();
+
+        if text.is_empty() || is_verbatim(text, lead, source_width) {
+            flush(&mut para, &mut blocks);
+            blocks.push(Block::Verbatim(line.to_string()));
+            prev_short = true;
+            continue;
+        }
+
+        let bullet = list_marker_len(text);
+        let starts_new = match &para {
+            None => true,
+            Some(p) => prev_short || bullet.is_some() || !p.continues(lead),
+        };
+
+        if starts_new {
+            flush(&mut para, &mut blocks);
+            let indent = lead + bullet.unwrap_or(0);
+            para = Some(Pending { first_indent: lead, indent, lines: 0, words: Vec::new() });
+        }
+
+        if let Some(p) = para.as_mut() {
+            if p.lines == 1 {
+                p.indent = lead;
+            }
+            p.lines += 1;
+            join_words(&mut p.words, text);
+        }
+        prev_short = line.chars().count() * 3 < source_width * 2;
+    }
+    flush(&mut para, &mut blocks);
+    blocks
+}
+
+/// Append the words of `text`, rejoining words hyphenated across lines.
+fn join_words(words: &mut Vec<String>, text: &str) {
+    let mut iter = text.split_whitespace();
+    if let (Some(last), Some(first)) = (words.last_mut(), text.split_whitespace().next()) {
+        let hyphenated = last.len() > 2
+            && last.ends_with('-')
+            && !last.ends_with("--")
+            && first.chars().next().is_some_and(|c| c.is_lowercase());
+        if hyphenated {
+            last.pop();
+            last.push_str(first);
+            iter.next();
+        }
+    }
+    words.extend(iter.map(String::from));
+}
+
+/// Lines that must keep their exact layout.
+fn is_verbatim(text: &str, lead: usize, source_width: usize) -> bool {
+    let len = text.chars().count();
+
+    // Box drawing, block shading and other high-bit art
+    if text.chars().any(|c| !c.is_ascii() && !c.is_alphanumeric()) {
+        return true;
+    }
+
+    // Rules and art made of repeated punctuation: "-----", "=*=*=*", "|  |"
+    let symbols = text.chars()
+        .filter(|c| !c.is_alphanumeric() && !c.is_whitespace() && !".,;:'\"!?()-".contains(*c))
+        .count();
+    let visible = text.chars().filter(|c| !c.is_whitespace()).count();
+    if symbols * 4 > visible || has_run(text, 4) {
+        return true;
+    }
+
+    // Tables: tabs or several wide column gaps
+    if text.contains('\t') || column_gaps(text) >= 2 || text.contains("     ") {
+        return true;
+    }
+
+    // Centered titles
+    if lead >= 8 && lead + len + 8 < source_width {
+        return true;
+    }
+
+    // Short all-caps headings and "SECTION:" style labels
+    let has_letters = text.chars().any(|c| c.is_alphabetic());
+    let all_caps = has_letters && !text.chars().any(|c| c.is_lowercase());
+    if len < 40 && (all_caps || text.ends_with(':')) && !text.ends_with('.') {
+        return true;
+    }
+
+    false
+}
+
+/// Count gaps of three or more spaces between words.
+fn column_gaps(text: &str) -> usize {
+    text.split("   ")
+        .filter(|s| !s.trim().is_empty())
+        .count()
+        .saturating_sub(1)
+}
+
+/// True if `text` has `n` or more of the same punctuation character in a row.
+fn has_run(text: &str, n: usize) -> bool {
+    let mut prev = None;
+    let mut count = 0;
+    for c in text.chars() {
+        if Some(c) == prev && !c.is_alphanumeric() && !c.is_whitespace() && c != '.' {
+            count += 1;
+            if count >= n {
+                return true;
+            }
+        } else {
+            prev = Some(c);
+            count = 1;
+        }
+    }
+    false
+}
+
+/// Width of a list marker ("- ", "* ", "1. ", "a) ") including its trailing space.
+fn list_marker_len(text: &str) -> Option<usize> {
+    let marker = text.split_whitespace().next()?;
+    if marker.len() == text.len() {
+        return None;
+    }
+    let is_marker = matches!(marker, "-" | "*" | "+" | "o" | "#")
+        || (marker.len() <= 4
+            && (marker.ends_with('.') || marker.ends_with(')'))
+            && marker[..marker.len() - 1].chars().all(|c| c.is_ascii_digit())
+            && marker.len() > 1)
+        || (marker.len() == 2
+            && marker.ends_with(')')
+            && marker.starts_with(|c: char| c.is_ascii_alphabetic()));
+    if !is_marker {
+        return None;
+    }
+    let rest = &text[marker.len()..];
+    Some(marker.chars().count() + rest.len() - rest.trim_start().len())
+}
+
+/// Greedy word fill with a first-line and hanging indent.
+pub fn fill(words: &[String], first_indent: usize, indent: usize, width: usize) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut line = " ".repeat(first_indent.min(width / 2));
+    let mut line_len = line.len();
+    let mut empty = true;
+
+    for word in words {
+        let word_len = word.chars().count();
+        if !empty && line_len + 1 + word_len > width {
+            out.push(std::mem::take(&mut line));
+            line = " ".repeat(indent.min(width / 2));
+            line_len = line.len();
+            empty = true;
+        }
+        if !empty {
+            line.push(' ');
+            line_len += 1;
+        }
+        if line_len + word_len > width {
+            // A single word longer than the line; fill what's left of this
+            // line, then chop the rest at the full width
+            let chars: Vec<char> = word.chars().collect();
+            let (first, rest) = chars.split_at(width.saturating_sub(line_len).max(1));
+            line.extend(first);
+            for chunk in rest.chunks(width.max(1)) {
+                out.push(std::mem::take(&mut line));
+                line = chunk.iter().collect();
+            }
+            line_len = line.chars().count();
+        } else {
+            line.push_str(word);
+            line_len += word_len;
+        }
+        empty = false;
+    }
+    if !empty {
+        out.push(line);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn rewraps_paragraphs() {
+        let text = lines(
+            "The phone company never expected anyone to whistle at exactly the\n\
+             right pitch, but a toy from a cereal box turned out to do just that.\n\
+             \n\
+             Short line.",
+        );
+        let out = reflow(&text, 40);
+        assert_eq!(out, [
+            "The phone company never expected anyone",
+            "to whistle at exactly the right pitch,",
+            "but a toy from a cereal box turned out",
+            "to do just that.",
+            "",
+            "Short line.",
+        ]);
+    }
+
+    #[test]
+    fn keeps_list_items_apart_with_hanging_indents() {
+        let text = lines(
+            "  - Pick up the handset and wait for the dial tone before starting\n    the tone.\n\
+             \x20 - Hang up.",
+        );
+        let out = reflow(&text, 40);
+        assert_eq!(out, [
+            "  - Pick up the handset and wait for the",
+            "    dial tone before starting the tone.",
+            "  - Hang up.",
+        ]);
+        assert_eq!(list_marker_len("1. First"), Some(3));
+        assert_eq!(list_marker_len("a) First"), Some(3));
+        assert_eq!(list_marker_len("1999. A year"), None);
+        assert_eq!(list_marker_len("Hello world"), None);
+    }
+
+    #[test]
+    fn leaves_tables_rules_and_titles_alone() {
+        let text = lines(
+            "                         THE BLUE BOX\n\
+             ---------------------------------------------------------------------------\n\
+             Tone      Frequency     Use\n\
+             2600      2600 Hz       Seize the trunk\n\
+             This paragraph is long enough to be prose and runs across the full width\n\
+             of the file so that it gets joined up with the line that follows it.",
+        );
+        let out = reflow(&text, 200);
+        assert_eq!(out[..4], text[..4]);
+        assert_eq!(out.len(), 5);
+        assert!(out[4].ends_with("the line that follows it."));
+    }
+
+    #[test]
+    fn rejoins_hyphenated_words() {
+        let mut words = vec!["tele-".to_string()];
+        join_words(&mut words, "phone lines");
+        assert_eq!(words, ["telephone", "lines"]);
+
+        // Dashes and capitalised words are real hyphens
+        let mut words = vec!["well--".to_string()];
+        join_words(&mut words, "anyway");
+        assert_eq!(words, ["well--", "anyway"]);
+        let mut words = vec!["Anti-".to_string()];
+        join_words(&mut words, "Soviet");
+        assert_eq!(words, ["Anti-", "Soviet"]);
+    }
+
+    #[test]
+    fn chops_long_words_at_the_full_width() {
+        let words = vec!["x".repeat(25)];
+        assert_eq!(fill(&words, 4, 0, 10), [
+            "    xxxxxx",
+            "xxxxxxxxxx",
+            "xxxxxxxxx",
+        ]);
+    }
+}
@@ -0,0 +1,153 @@
+//! robots.txt rules for one site
+//!
+//! Follows RFC 9309: the group naming our product token wins over `*`,
+//! the longest matching rule decides, and `Allow` wins a tie. `Crawl-delay`
+//! isn't in the RFC but is widely used, so it's honoured too.
+
+use std::time::Duration;
+
+#[derive(Debug, Default, Clone)]
+pub struct Robots {
+    /// (allow, path pattern), from the group that applies to us
+    rules: Vec<(bool, String)>,
+    pub crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    /// The rules in `text` for the crawler calling itself `agent`.
+    pub fn parse(text: &str, agent: &str) -> Self {
+        // The product token is the user agent up to the first slash or space
+        let token = agent.split(['/', ' ']).next().unwrap_or(agent).to_lowercase();
+        let mut ours: Option<Robots> = None;
+        let mut anyone: Option<Robots> = None;
+
+        // Consecutive user-agent lines share the group that follows them
+        let mut agents: Vec<String> = Vec::new();
+        let mut group = Robots::default();
+        let mut in_rules = false;
+        let mut finish = |agents: &[String], group: Robots| {
+            if agents.contains(&token) {
+                ours.get_or_insert_with(Robots::default).merge(group);
+            } else if agents.iter().any(|a| a == "*") {
+                anyone.get_or_insert_with(Robots::default).merge(group);
+            }
+        };
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let (key, value) = (key.trim().to_lowercase(), value.trim());
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        finish(&agents, std::mem::take(&mut group));
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    agents.push(value.to_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty Disallow allows everything
+                    if !value.is_empty() {
+                        group.rules.push((key == "allow", value.to_string()));
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    group.crawl_delay = value.parse::<f64>().ok().filter(|d| d.is_finite() && *d >= 0.0)
+                        .map(Duration::from_secs_f64);
+                }
+                _ => {}
+            }
+        }
+        finish(&agents, group);
+        ours.or(anyone).unwrap_or_default()
+    }
+
+    fn merge(&mut self, other: Robots) {
+        self.rules.extend(other.rules);
+        self.crawl_delay = self.crawl_delay.max(other.crawl_delay);
+    }
+
+    /// Whether `path` (with any query) may be fetched.
+    pub fn allows(&self, path: &str) -> bool {
+        let path = if path.is_empty() { "/" } else { path };
+        self.rules.iter()
+            .filter(|(_, pattern)| matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+}
+
+/// A robots.txt path pattern: a prefix, with `*` for any run of characters
+/// and a trailing `$` anchoring the end.
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let Some(mut rest) = path.strip_prefix(parts[0]) else {
+        return false;
+    };
+    let after_stars = &parts[1..];
+    for (i, part) in after_stars.iter().enumerate() {
+        if anchored && i + 1 == after_stars.len() {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "\
+# Be nice
+User-agent: *
+Disallow: /cgi-bin/
+Disallow: /private
+Allow: /private/readme.txt
+Crawl-delay: 2
+
+User-agent: BadBot
+User-agent: TextfilesBrowser
+Disallow: /*.zip$
+Disallow: /tmp/
+Crawl-delay: 0.5
+";
+
+    #[test]
+    fn falls_back_to_the_wildcard_group() {
+        let robots = Robots::parse(ROBOTS, "SomeoneElse/2.0");
+        assert!(robots.allows("/hacking/phreak.txt"));
+        assert!(!robots.allows("/cgi-bin/search?q=x"));
+        assert!(!robots.allows("/private/notes.txt"));
+        assert!(robots.allows("/private/readme.txt"));
+        assert_eq!(robots.crawl_delay, Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn prefers_our_own_group() {
+        let robots = Robots::parse(ROBOTS, "TextfilesBrowser/1.0");
+        assert!(robots.allows("/cgi-bin/search"));
+        assert!(!robots.allows("/games/doom.zip"));
+        assert!(robots.allows("/games/doom.zip.txt"));
+        assert!(!robots.allows("/tmp/x"));
+        assert_eq!(robots.crawl_delay, Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn empty_or_missing_rules_allow_everything() {
+        assert!(Robots::parse("User-agent: *\nDisallow:\n", "x").allows("/anything"));
+        assert!(Robots::default().allows("/"));
+    }
+}
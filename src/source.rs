@@ -0,0 +1,195 @@
+//! Where pages come from, chosen by URL scheme
+//!
+//! Archive members and Wayback snapshots are layered on top of these by
+//! `browser::load_page`, so every source gets them for free.
+
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::fetcher::{Fetcher, Response};
+use crate::local::LocalSource;
+use crate::mirror::Mirrors;
+use crate::parser::DirEntry;
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    /// Listed with `Source::list` rather than read and parsed
+    pub is_dir: bool,
+}
+
+/// How a read treats cached copies and size limits
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReadOptions {
+    /// Check with the origin even if a cached copy would otherwise do
+    pub revalidate: bool,
+    /// Read the file however large it is
+    pub any_size: bool,
+    /// Only the start is wanted, e.g. for a preview: stop after
+    /// `fetcher::HEAD_LEN` bytes, and leave the cache alone
+    pub head: bool,
+    /// Take the cached copy however old it is, without asking the origin
+    pub cached: bool,
+}
+
+pub trait Source: Send + Sync {
+    /// What's at `url`, as far as the source can tell without reading it.
+    fn metadata(&self, url: &str) -> Result<Metadata>;
+
+    /// The entries of the directory at `url`.
+    fn list(&self, url: &str) -> Result<Vec<DirEntry>>;
+
+    /// The contents of the file at `url`.
+    fn read(&self, url: &str, options: ReadOptions) -> Result<Response>;
+
+    /// Whether a copy of `url` is kept to read with `ReadOptions::cached`.
+    fn is_cached(&self, _url: &str) -> bool {
+        false
+    }
+}
+
+/// The source for each URL scheme. Plain paths count as `file`.
+#[derive(Clone, Default)]
+pub struct Sources {
+    by_scheme: BTreeMap<String, Arc<dyn Source>>,
+    /// Passed to every read
+    options: ReadOptions,
+    /// Copies of the site that HTTP reads fail over to
+    mirrors: Option<Arc<Mirrors>>,
+    /// Largest file a read returns, unless `options` says any size will do
+    max_download: Option<u64>,
+}
+
+impl Sources {
+    /// HTTP(S) and the local filesystem, with HTTP going through the
+    /// mirrors if there are any.
+    pub fn new(config: &Config) -> Result<Self> {
+        let http: Arc<dyn Source> = Arc::new(Fetcher::new(&config.network, Cache::open(&config.cache))?);
+        let mut sources = Self { max_download: config.network.max_download(), ..Self::default() };
+        sources.register("http", http.clone());
+        sources.register("https", http);
+        sources.register("file", Arc::new(LocalSource::new(config.network.max_download())));
+        if !config.mirrors.urls.is_empty() {
+            let mirrors = Arc::new(Mirrors::new(&config.mirrors, sources.clone()));
+            sources.register("http", mirrors.clone());
+            sources.register("https", mirrors.clone());
+            sources.mirrors = Some(mirrors);
+        }
+        Ok(sources)
+    }
+
+    /// Serve `scheme` URLs from `source`, replacing any existing one.
+    pub fn register(&mut self, scheme: &str, source: Arc<dyn Source>) {
+        self.by_scheme.insert(scheme.to_string(), source);
+    }
+
+    pub fn get(&self, url: &str) -> Result<&dyn Source> {
+        let scheme = url.split_once("://").map_or("file", |(scheme, _)| scheme);
+        match self.by_scheme.get(&scheme.to_lowercase()) {
+            Some(source) => Ok(source.as_ref()),
+            None => bail!("Don't know how to open {scheme}:// URLs"),
+        }
+    }
+
+    /// `url` as the site itself names it, if it's on a mirror.
+    pub fn canonical(&self, url: &str) -> String {
+        self.mirrors.as_ref().map_or_else(|| url.to_string(), |mirrors| mirrors.canonical(url))
+    }
+
+    /// The same sources, reading with `options`.
+    pub fn with_options(&self, options: ReadOptions) -> Self {
+        Self { options, ..self.clone() }
+    }
+
+    /// How big a file these reads accept, if there's a limit.
+    pub fn limit(&self) -> Option<u64> {
+        self.max_download.filter(|_| !self.options.any_size)
+    }
+
+    pub fn read(&self, url: &str) -> Result<Response> {
+        self.get(url)?.read(url, self.options)
+    }
+
+    pub fn is_cached(&self, url: &str) -> bool {
+        self.get(url).is_ok_and(|source| source.is_cached(url))
+    }
+}
+
+/// Files held in memory, keyed by URL, for test fixtures. Directories are
+/// implied by the files under them.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemorySource {
+    files: BTreeMap<String, (Vec<u8>, Option<String>)>,
+}
+
+#[cfg(test)]
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file, with the content type an HTTP server would give it.
+    pub fn insert(&mut self, url: &str, bytes: impl Into<Vec<u8>>, content_type: Option<&str>) {
+        self.files.insert(url.to_string(), (bytes.into(), content_type.map(String::from)));
+    }
+
+    pub fn with(mut self, url: &str, bytes: impl Into<Vec<u8>>, content_type: Option<&str>) -> Self {
+        self.insert(url, bytes, content_type);
+        self
+    }
+
+    fn dir_prefix(url: &str) -> String {
+        format!("{}/", url.trim_end_matches('/'))
+    }
+}
+
+#[cfg(test)]
+impl Source for MemorySource {
+    fn metadata(&self, url: &str) -> Result<Metadata> {
+        if self.files.contains_key(url) {
+            return Ok(Metadata { is_dir: false });
+        }
+        let prefix = Self::dir_prefix(url);
+        if self.files.keys().any(|k| k.starts_with(&prefix)) {
+            return Ok(Metadata { is_dir: true });
+        }
+        Err(crate::error::LoadError::Status(404).into())
+    }
+
+    fn list(&self, url: &str) -> Result<Vec<DirEntry>> {
+        let prefix = Self::dir_prefix(url);
+        let mut entries: Vec<DirEntry> = Vec::new();
+        for (key, (bytes, _)) in self.files.range(prefix.clone()..) {
+            let Some(rest) = key.strip_prefix(&prefix) else {
+                break;
+            };
+            let (name, is_dir) = match rest.split_once('/') {
+                Some((dir, _)) => (dir, true),
+                None => (rest, false),
+            };
+            if name.is_empty() || entries.last().is_some_and(|e| e.name == name) {
+                continue;
+            }
+            entries.push(DirEntry {
+                name: name.to_string(),
+                url: format!("{prefix}{name}{}", if is_dir { "/" } else { "" }),
+                description: String::new(),
+                size: (!is_dir).then_some(bytes.len() as u64),
+                is_dir,
+            });
+        }
+        Ok(entries)
+    }
+
+    fn read(&self, url: &str, options: ReadOptions) -> Result<Response> {
+        match self.files.get(url) {
+            Some((bytes, content_type)) => {
+                let len = if options.head { bytes.len().min(crate::fetcher::HEAD_LEN) } else { bytes.len() };
+                Ok(Response { bytes: bytes[..len].to_vec(), content_type: content_type.clone(), changed: false })
+            }
+            None => Err(crate::error::LoadError::Status(404).into()),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! A browsing context: its own history, listing and viewer state
+
+use crate::binary::{self, Binary};
+use crate::browser::{Browser, Content, Page};
+use crate::html::Document;
+use crate::links::{self, Link};
+use crate::parser::DirEntry;
+use crate::reflow::{self, WrapMode};
+use crate::ui::Mode;
+use ratatui::widgets::ListState;
+use std::borrow::Cow;
+
+/// What the viewer shows, kept so it can be laid out again at a new width
+pub enum Body {
+    /// Plain text, in `text_lines`
+    Text,
+    Document(Document),
+    Binary(Binary),
+}
+
+pub struct Tab {
+    pub browser: Browser,
+    pub mode: Mode,
+    pub entries: Vec<DirEntry>,
+    pub list_state: ListState,
+    pub title: String,
+    pub text_lines: Vec<String>,
+    pub body: Body,
+    /// The viewer's lines, except for binaries
+    pub wrapped_lines: Vec<String>,
+    /// Bytes per hex dump row at the current width
+    dump_row_len: usize,
+    pub scroll: usize,
+    pub links: Vec<Link>,
+    pub focused_link: Option<usize>,
+}
+
+impl Tab {
+    pub fn new(browser: Browser) -> Self {
+        Self {
+            browser,
+            mode: Mode::Browser,
+            entries: Vec::new(),
+            list_state: ListState::default(),
+            title: String::new(),
+            text_lines: Vec::new(),
+            body: Body::Text,
+            wrapped_lines: Vec::new(),
+            dump_row_len: binary::row_len(0),
+            scroll: 0,
+            links: Vec::new(),
+            focused_link: None,
+        }
+    }
+
+    pub fn apply_page(&mut self, page: Page, width: usize, wrap: WrapMode) {
+        self.title = page.title;
+        match page.content {
+            Content::Directory(entries) => {
+                self.entries = entries;
+                self.list_state.select(if self.entries.is_empty() { None } else { Some(0) });
+                self.mode = Mode::Browser;
+            }
+            Content::TextFile(text) => {
+                self.text_lines = text.lines().map(String::from).collect();
+                self.show(Body::Text, width, wrap);
+            }
+            Content::Document(document) => {
+                self.text_lines.clear();
+                self.show(Body::Document(document), width, wrap);
+            }
+            Content::Binary(data) => {
+                self.text_lines.clear();
+                self.show(Body::Binary(data), width, wrap);
+            }
+        }
+    }
+
+    fn show(&mut self, body: Body, width: usize, wrap: WrapMode) {
+        self.body = body;
+        self.rewrap(width, wrap);
+        self.scroll = 0;
+        self.mode = Mode::Viewer;
+    }
+
+    pub fn selected_entry(&self) -> Option<&DirEntry> {
+        self.list_state.selected().and_then(|i| self.entries.get(i))
+    }
+
+    /// Select entry `i`, clamped to the list.
+    pub fn select_index(&mut self, i: usize) {
+        if self.entries.is_empty() { return; }
+        self.list_state.select(Some(i.min(self.entries.len() - 1)));
+    }
+
+    pub fn next(&mut self) {
+        let i = self.list_state.selected().map(|i| i + 1).unwrap_or(0);
+        self.select_index(i);
+    }
+
+    pub fn previous(&mut self) {
+        let i = self.list_state.selected().map(|i| i.saturating_sub(1)).unwrap_or(0);
+        self.select_index(i);
+    }
+
+    pub fn page_down(&mut self, n: usize) {
+        let i = self.list_state.selected().map(|i| i.saturating_add(n)).unwrap_or(0);
+        self.select_index(i);
+    }
+
+    pub fn page_up(&mut self, n: usize) {
+        let i = self.list_state.selected().map(|i| i.saturating_sub(n)).unwrap_or(0);
+        self.select_index(i);
+    }
+
+    pub fn home(&mut self) {
+        self.select_index(0);
+    }
+
+    pub fn end(&mut self) {
+        self.select_index(usize::MAX);
+    }
+
+    pub fn scroll_up(&mut self, n: usize) {
+        self.scroll = self.scroll.saturating_sub(n);
+    }
+
+    pub fn scroll_down(&mut self, n: usize, visible_height: usize) {
+        let max_scroll = self.line_count().saturating_sub(visible_height);
+        self.scroll = self.scroll.saturating_add(n).min(max_scroll);
+    }
+
+    pub fn scroll_home(&mut self) {
+        self.scroll = 0;
+    }
+
+    pub fn scroll_end(&mut self, visible_height: usize) {
+        self.scroll = self.line_count().saturating_sub(visible_height);
+    }
+
+    /// Lines in the viewer.
+    pub fn line_count(&self) -> usize {
+        match &self.body {
+            Body::Binary(data) => binary::dump_rows(&data.bytes, self.dump_row_len),
+            _ => self.wrapped_lines.len(),
+        }
+    }
+
+    /// Up to `n` viewer lines from `start`. Hex dumps are made a row at a
+    /// time, only for the lines asked for.
+    pub fn lines(&self, start: usize, n: usize) -> Vec<Cow<'_, str>> {
+        match &self.body {
+            Body::Binary(data) => (start..self.line_count().min(start.saturating_add(n)))
+                .map(|row| Cow::Owned(binary::dump_row(&data.bytes, row, self.dump_row_len)))
+                .collect(),
+            _ => self.wrapped_lines.iter().skip(start).take(n).map(|line| Cow::Borrowed(line.as_str())).collect(),
+        }
+    }
+
+    /// Re-wrap the file to `width`. Documents are always filled to the
+    /// width and binaries dumped, whatever the wrap mode.
+    pub fn rewrap(&mut self, width: usize, wrap: WrapMode) {
+        self.dump_row_len = binary::row_len(width);
+        self.wrapped_lines = match (&self.body, wrap) {
+            (Body::Document(document), _) => document.render(width),
+            (Body::Binary(_), _) => Vec::new(),
+            (Body::Text, WrapMode::Hard) => self.text_lines.iter()
+                .flat_map(|line| reflow::wrap_line(line, width))
+                .collect(),
+            (Body::Text, WrapMode::Reflow) => reflow::reflow(&self.text_lines, width),
+        };
+        self.links = match self.body {
+            Body::Binary(_) => Vec::new(),
+            _ => links::find_links(&self.wrapped_lines),
+        };
+        self.focused_link = None;
+    }
+
+    /// Focus the next or previous link, starting from the edge of the view when
+    /// nothing is focused, and scroll the focused link into view.
+    pub fn focus_link(&mut self, forward: bool, visible_height: usize) {
+        if self.links.is_empty() {
+            return;
+        }
+        let next = match self.focused_link {
+            Some(i) if forward => (i + 1) % self.links.len(),
+            Some(i) => (i + self.links.len() - 1) % self.links.len(),
+            None if forward => self.links.iter().position(|l| l.line >= self.scroll).unwrap_or(0),
+            None => self.links.iter().rposition(|l| l.line < self.scroll + visible_height)
+                .unwrap_or(self.links.len() - 1),
+        };
+        self.focused_link = Some(next);
+
+        let line = self.links[next].line;
+        if line < self.scroll {
+            self.scroll = line;
+        } else if line >= self.scroll + visible_height {
+            self.scroll = (line + 1).saturating_sub(visible_height);
+        }
+    }
+
+    pub fn current_link(&self) -> Option<&Link> {
+        self.focused_link.and_then(|i| self.links.get(i))
+    }
+
+    /// Title for the tab bar
+    pub fn label(&self) -> &str {
+        if self.title.is_empty() { &self.browser.current_url } else { &self.title }
+    }
+}
@@ -0,0 +1,172 @@
+//! Color themes
+
+use crate::config::ThemeColors;
+use anyhow::{Context, Result};
+use ratatui::style::{Color, Modifier, Style};
+use std::str::FromStr;
+
+pub const DEFAULT_THEME: &str = "phosphor";
+
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub name: String,
+    pub bright: Color,
+    pub normal: Color,
+    pub dim: Color,
+    pub background: Color,
+    pub alert: Color,
+}
+
+impl Theme {
+    /// Green phosphor CRT
+    pub fn phosphor() -> Self {
+        Self {
+            name: "phosphor".into(),
+            bright: Color::Rgb(57, 255, 20),
+            normal: Color::Rgb(0, 200, 0),
+            dim: Color::Rgb(0, 140, 0),
+            background: Color::Rgb(0, 10, 0),
+            alert: Color::Red,
+        }
+    }
+
+    /// Amber monochrome monitor
+    pub fn amber() -> Self {
+        Self {
+            name: "amber".into(),
+            bright: Color::Rgb(255, 191, 0),
+            normal: Color::Rgb(230, 150, 0),
+            dim: Color::Rgb(150, 95, 0),
+            background: Color::Rgb(12, 6, 0),
+            alert: Color::Rgb(255, 80, 40),
+        }
+    }
+
+    /// Paper-white monochrome monitor
+    pub fn white() -> Self {
+        Self {
+            name: "white".into(),
+            bright: Color::Rgb(255, 255, 255),
+            normal: Color::Rgb(205, 205, 200),
+            dim: Color::Rgb(130, 130, 125),
+            background: Color::Rgb(12, 12, 14),
+            alert: Color::Rgb(255, 90, 90),
+        }
+    }
+
+    /// IBM PC text mode: white on blue
+    pub fn ibm() -> Self {
+        Self {
+            name: "ibm".into(),
+            bright: Color::Rgb(255, 255, 85),
+            normal: Color::Rgb(170, 170, 170),
+            dim: Color::Rgb(85, 255, 255),
+            background: Color::Rgb(0, 0, 170),
+            alert: Color::Rgb(255, 85, 85),
+        }
+    }
+
+    /// Whatever the terminal's own palette is
+    pub fn terminal() -> Self {
+        Self {
+            name: "terminal".into(),
+            bright: Color::Reset,
+            normal: Color::Reset,
+            dim: Color::DarkGray,
+            background: Color::Reset,
+            alert: Color::Red,
+        }
+    }
+
+    pub fn builtin() -> Vec<Theme> {
+        vec![Self::phosphor(), Self::amber(), Self::white(), Self::ibm(), Self::terminal()]
+    }
+
+    pub fn from_colors(name: &str, colors: &ThemeColors) -> Result<Self> {
+        let parse = |field: &str, value: &str| {
+            Color::from_str(value)
+                .map_err(|_| anyhow::anyhow!("invalid color {value:?}"))
+                .with_context(|| format!("theme {name:?}: {field}"))
+        };
+        Ok(Self {
+            name: name.to_string(),
+            bright: parse("bright", &colors.bright)?,
+            normal: parse("normal", &colors.normal)?,
+            dim: parse("dim", &colors.dim)?,
+            background: parse("background", &colors.background)?,
+            alert: match &colors.alert {
+                Some(alert) => parse("alert", alert)?,
+                None => Color::Red,
+            },
+        })
+    }
+
+    /// The theme called `name` among `themes`.
+    pub fn find(themes: &[Theme], name: &str) -> Result<Self> {
+        themes.iter().find(|t| t.name == name).cloned().with_context(|| format!("Unknown theme {name:?}"))
+    }
+
+    /// Style for the highlighted row in lists.
+    pub fn selection(&self) -> Style {
+        if self.background == Color::Reset {
+            // Terminal colors are unknown, so let the terminal invert them
+            Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD)
+        } else {
+            Style::default().fg(self.background).bg(self.bright)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn colors(bright: &str, alert: Option<&str>) -> ThemeColors {
+        ThemeColors {
+            bright: bright.to_string(),
+            normal: "lightgreen".to_string(),
+            dim: "DarkGray".to_string(),
+            background: "#002b36".to_string(),
+            alert: alert.map(String::from),
+        }
+    }
+
+    #[test]
+    fn parses_named_and_hex_colors() {
+        let theme = Theme::from_colors("mine", &colors("#fdf6e3", Some("red"))).unwrap();
+        assert_eq!(theme.bright, Color::Rgb(0xfd, 0xf6, 0xe3));
+        assert_eq!((theme.normal, theme.dim), (Color::LightGreen, Color::DarkGray));
+        assert_eq!((theme.background, theme.alert), (Color::Rgb(0, 0x2b, 0x36), Color::Red));
+        let theme = Theme::from_colors("mine", &colors("white", None)).unwrap();
+        assert_eq!((theme.bright, theme.alert), (Color::White, Color::Red));
+    }
+
+    #[test]
+    fn rejects_bad_colors() {
+        for bad in ["#fdf6e", "#gggggg", "chartreuse-ish", ""] {
+            let e = Theme::from_colors("mine", &colors(bad, None)).unwrap_err();
+            assert_eq!(format!("{e:#}"), format!("theme \"mine\": bright: invalid color {bad:?}"));
+        }
+        assert!(Theme::from_colors("mine", &colors("white", Some("#12"))).is_err());
+    }
+
+    #[test]
+    fn picks_the_configured_theme() {
+        let mut config = Config::default();
+        let themes = config.themes().unwrap();
+        assert_eq!(Theme::find(&themes, &config.theme).unwrap().name, DEFAULT_THEME);
+        let e = Theme::find(&themes, "solarized").unwrap_err();
+        assert_eq!(e.to_string(), "Unknown theme \"solarized\"");
+
+        // User themes join the built-in ones and replace those they share a name with
+        config.themes.insert("solarized".to_string(), colors("#fdf6e3", None));
+        config.themes.insert("amber".to_string(), colors("white", None));
+        let themes = config.themes().unwrap();
+        assert_eq!(themes.len(), Theme::builtin().len() + 1);
+        assert!(Theme::find(&themes, "solarized").is_ok());
+        assert_eq!(Theme::find(&themes, "amber").unwrap().bright, Color::White);
+        config.themes.insert("broken".to_string(), colors("nope", None));
+        assert!(config.themes().is_err());
+    }
+}
@@ -1,35 +1,47 @@
-//! Terminal UI - Green phosphor CRT aesthetic
+//! Terminal UI - CRT-style chrome drawn in the configured color theme
 
-use crate::browser::{Browser, Content, Page};
-use crate::parser::DirEntry;
+use crate::binary;
+use crate::browser::{self, Browser, Content, Visit};
+use crate::config::Config;
+use crate::error::{self, Suggestion};
+use crate::keymap::{Action, Keymap};
+use crate::links::LinkKind;
+use crate::loading::{Destination, Loading, Outcome};
+use crate::local;
+use crate::preview::{Preview, PreviewState};
+use crate::progress::Progress;
+use crate::reflow::{self, WrapMode};
+use crate::source::ReadOptions;
+use crate::tab::{Body, Tab};
+use crate::theme::Theme;
+use crate::wayback;
 use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Position, Rect},
+    style::{Modifier, Style},
     symbols::border,
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Padding, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState},
     Frame,
 };
+use std::cmp::Ordering;
+use std::io::Write;
+use std::time::{Duration, Instant};
 
-// Phosphor green palette
-const GREEN_BRIGHT: Color = Color::Rgb(57, 255, 20);
-const GREEN_NORMAL: Color = Color::Rgb(0, 200, 0);
-const GREEN_DIM: Color = Color::Rgb(0, 140, 0);
-const BLACK: Color = Color::Rgb(0, 10, 0);
+/// How long a status bar message stays up, in ticks (~2s)
+const FLASH_TICKS: u64 = 40;
 
-fn wrap_line(line: &str, width: usize) -> Vec<String> {
-    if line.is_empty() {
-        return vec![String::new()];
-    }
-    let chars: Vec<char> = line.chars().collect();
-    if chars.len() <= width {
-        return vec![line.to_string()];
-    }
-    chars.chunks(width)
-        .map(|chunk| chunk.iter().collect())
-        .collect()
-}
+/// Terminals shorter than this get the one-line banner instead of the art
+const FULL_HEADER_MIN_HEIGHT: u16 = 30;
+
+/// Lines or entries moved per mouse wheel notch
+const WHEEL_STEP: usize = 3;
+
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Cells in the download progress bar
+const PROGRESS_WIDTH: u64 = 20;
 
 fn marquee(text: &str, width: usize, offset: usize) -> String {
     let chars: Vec<char> = text.chars().collect();
@@ -62,256 +74,835 @@ const HEADER: &str = r#"
              ░  ░ ░    ░                   ░      ░  ░   ░  ░      ░
 ══════════════════════════════ .COM ═══════════════════════════════════"#;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Mode {
     Browser,
     Viewer,
 }
 
+/// Something clickable in the last drawn frame
+#[derive(Clone)]
+pub enum Target {
+    Back,
+    Help,
+    Url(String),
+    Tab(usize),
+    /// A link in the viewer, by index into the tab's links
+    Link(usize),
+}
+
+/// A link drawn in the last frame, to be re-emitted as an OSC 8 hyperlink
+pub struct Hyperlink {
+    pub area: Rect,
+    pub url: String,
+    pub text: String,
+    pub style: Style,
+}
+
+/// A failed load on show; `details` switches to the full error chain
+pub struct ErrorView {
+    pub error: anyhow::Error,
+    /// What was being loaded
+    pub url: String,
+    pub details: bool,
+    /// A copy of `url` is in the cache
+    pub cached: bool,
+}
+
+/// Wayback captures of a page to choose from; the first row is the live site
+pub struct SnapshotPicker {
+    pub url: String,
+    /// Timestamps, newest first
+    pub snapshots: Vec<String>,
+    pub state: ListState,
+}
+
 pub struct App {
-    pub browser: Browser,
-    pub mode: Mode,
-    pub entries: Vec<DirEntry>,
-    pub list_state: ListState,
-    pub title: String,
-    pub text_lines: Vec<String>,
-    pub wrapped_lines: Vec<String>,
-    pub scroll: usize,
+    pub tabs: Vec<Tab>,
+    pub active: usize,
     pub view_width: u16,
-    pub error: Option<String>,
+    pub wrap_mode: WrapMode,
+    pub page_size: usize,
+    pub error: Option<ErrorView>,
+    /// Loads in flight, at most one per tab besides those opening new tabs
+    pub loads: Vec<Loading>,
+    pub keymap: Keymap,
+    pub preview: Preview,
+    pub show_help: bool,
+    pub snapshot_picker: Option<SnapshotPicker>,
+    pub header_collapsed: bool,
+    pub quit: bool,
+    pub content_area: Rect,
+    pub targets: Vec<(Rect, Target)>,
+    pub hyperlinks: Vec<Hyperlink>,
+    last_click: Option<(Instant, usize)>,
+    dragging_scrollbar: bool,
+    pub flash: Option<(String, u64)>,
+    pub themes: Vec<Theme>,
+    pub theme: Theme,
     pub tick: u64,
     pub marquee_offset: usize,
+    /// Tab, URL and selection the marquee started scrolling for
+    marquee_anchor: (usize, String, Option<usize>),
 }
 
 impl App {
-    pub fn new() -> Self {
-        Self {
-            browser: Browser::new().expect("Failed to create browser"),
-            mode: Mode::Browser,
-            entries: Vec::new(),
-            list_state: ListState::default(),
-            title: String::new(),
-            text_lines: Vec::new(),
-            wrapped_lines: Vec::new(),
-            scroll: 0,
+    pub fn new(config: &Config) -> Result<Self> {
+        Self::with_browser(config, Browser::new(config)?)
+    }
+
+    /// An app whose first tab is `browser`, e.g. one reading fixtures.
+    pub fn with_browser(config: &Config, browser: Browser) -> Result<Self> {
+        let themes = config.themes()?;
+        let theme = Theme::find(&themes, &config.theme)?;
+        Ok(Self {
+            preview: Preview::new(browser.sources(), config.preview),
+            tabs: vec![Tab::new(browser)],
+            active: 0,
             view_width: 80,
+            wrap_mode: config.wrap,
+            page_size: config.page_size.max(1),
             error: None,
+            loads: Vec::new(),
+            keymap: Keymap::from_config(config)?,
+            show_help: false,
+            snapshot_picker: None,
+            header_collapsed: config.compact_header,
+            quit: false,
+            content_area: Rect::default(),
+            targets: Vec::new(),
+            hyperlinks: Vec::new(),
+            last_click: None,
+            dragging_scrollbar: false,
+            flash: None,
+            themes,
+            theme,
             tick: 0,
             marquee_offset: 0,
-        }
+            marquee_anchor: (0, String::new(), None),
+        })
+    }
+
+    pub fn tab(&self) -> &Tab {
+        &self.tabs[self.active]
+    }
+
+    pub fn tab_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.active]
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.tab().mode
+    }
+
+    /// Text rows inside the content pane, as of the last frame
+    pub fn visible_height(&self) -> usize {
+        self.content_area.height.saturating_sub(2) as usize // borders
+    }
+
+    /// Width available to viewer text
+    fn text_width(&self) -> usize {
+        let width = self.view_width.saturating_sub(3) as usize; // borders + scrollbar
+        width.max(40) // minimum sanity
     }
 
     pub fn load_home(&mut self) -> Result<()> {
-        self.navigate_to("http://textfiles.com/directory.html")
+        let url = self.tab().browser.home_url.clone();
+        self.navigate_to(&url)
     }
 
     pub fn navigate_to(&mut self, url: &str) -> Result<()> {
-        match self.browser.navigate(url) {
-            Ok(page) => {
-                self.marquee_offset = 0;
-                self.apply_page(page);
-            }
-            Err(e) => self.error = Some(e.to_string()),
-        }
+        self.start_load(url, Destination::Tab(self.active, Visit::Forward));
         Ok(())
     }
 
-    fn apply_page(&mut self, page: Page) {
-        self.title = page.title;
-        match page.content {
-            Content::Directory(entries) => {
-                self.entries = entries;
-                self.list_state.select(if self.entries.is_empty() { None } else { Some(0) });
-                self.mode = Mode::Browser;
+    /// Load `url` in the background, replacing any load still running.
+    /// Reloads check cached copies with the server.
+    fn start_load(&mut self, url: &str, destination: Destination) {
+        let revalidate = matches!(destination, Destination::Tab(_, Visit::Reload));
+        self.start_load_with(url, destination, ReadOptions { revalidate, ..ReadOptions::default() });
+    }
+
+    fn start_load_with(&mut self, url: &str, destination: Destination, options: ReadOptions) {
+        if destination.in_place() {
+            self.loads.retain(|l| !(l.destination.in_place() && l.destination.tab() == destination.tab()));
+        }
+        let loader = self.tabs[destination.tab()].browser.loader();
+        self.loads.push(Loading::start(loader, url, destination, options));
+    }
+
+    /// The loads started from the active tab.
+    pub fn active_loads(&self) -> impl Iterator<Item = &Loading> {
+        self.loads.iter().filter(|l| l.destination.tab() == self.active)
+    }
+
+    /// Put the results of finished loads where they were going.
+    pub fn poll_loads(&mut self) {
+        // One at a time, as a new tab renumbers the ones after it
+        while let Some((i, result)) = self.loads.iter_mut().enumerate().find_map(|(i, l)| l.poll().map(|r| (i, r))) {
+            let loading = self.loads.remove(i);
+            self.finish_load(loading, result);
+        }
+    }
+
+    fn finish_load(&mut self, loading: Loading, result: Result<Outcome>) {
+        let tab = loading.destination.tab();
+        if tab >= self.tabs.len() {
+            return;
+        }
+        // Whatever needs an answer is asked in the tab it's about
+        let loaded = match result {
+            Ok(Outcome::Page(loaded)) => loaded,
+            Ok(Outcome::Snapshots(snapshots)) => {
+                self.switch_tab(tab);
+                return self.pick_snapshot(&loading.url, snapshots);
+            }
+            Err(e) => {
+                self.switch_tab(tab);
+                return match loading.destination {
+                    Destination::Snapshots(_) => self.fail(e, wayback::original(&loading.url)),
+                    _ => self.fail(e, &loading.url),
+                };
+            }
+        };
+        let i = match loading.destination {
+            // Only ever has snapshots
+            Destination::Snapshots(_) => return,
+            Destination::Tab(i, visit) => {
+                self.tabs[i].browser.arrive(loaded.url, visit);
+                i
             }
-            Content::TextFile(text) => {
-                self.text_lines = text.lines().map(String::from).collect();
-                self.rewrap_lines();
-                self.scroll = 0;
-                self.mode = Mode::Viewer;
+            Destination::NewTab(from) => {
+                let mut tab = Tab::new(self.tabs[from].browser.fork());
+                tab.browser.arrive(loaded.url, Visit::Forward);
+                self.tabs.insert(from + 1, tab);
+                self.renumber_loads(|i| Some(if i > from { i + 1 } else { i }));
+                self.switch_tab(from + 1);
+                from + 1
             }
+        };
+        let (width, wrap) = (self.text_width(), self.wrap_mode);
+        self.tabs[i].apply_page(loaded.page, width, wrap);
+        if let Some(notice) = loaded.notice {
+            self.flash(notice);
         }
     }
 
-    pub fn select(&mut self) -> Result<()> {
-        if let Some(i) = self.list_state.selected() {
-            if let Some(entry) = self.entries.get(i) {
-                let url = entry.url.clone();
-                self.navigate_to(&url)?;
+    fn fail(&mut self, error: anyhow::Error, url: &str) {
+        let cached = self.tab().browser.is_cached(url);
+        self.error = Some(ErrorView { error, url: url.to_string(), details: false, cached });
+    }
+
+    /// What the error box offers, leaving out what can't work for its URL.
+    pub fn suggestions(&self) -> Vec<Suggestion> {
+        let Some(view) = &self.error else {
+            return Vec::new();
+        };
+        let all = error::classify(&view.error).map_or(&[Suggestion::Retry, Suggestion::Up][..], |e| e.suggestions());
+        all.iter()
+            .copied()
+            .filter(|s| match s {
+                Suggestion::Retry | Suggestion::Download => true,
+                Suggestion::Cached => view.cached,
+                Suggestion::Snapshot => view.url.starts_with("http"),
+                Suggestion::Up => browser::parent_url(&view.url).is_some(),
+            })
+            .collect()
+    }
+
+    fn error_key(&mut self, key: KeyEvent) -> Result<()> {
+        let chosen = self.suggestions().into_iter().find(|s| key.code == KeyCode::Char(s.key()));
+        let Some(view) = self.error.as_mut() else {
+            return Ok(());
+        };
+        if key.code == KeyCode::Char('d') {
+            view.details = !view.details;
+            return Ok(());
+        }
+        let url = view.url.clone();
+        self.error = None;
+        match chosen {
+            Some(Suggestion::Retry) if url == self.tab().browser.current_url => self.refresh()?,
+            Some(Suggestion::Retry) => self.navigate_to(&url)?,
+            Some(Suggestion::Snapshot) => self.snapshots_of(&url),
+            Some(Suggestion::Up) => {
+                if let Some(parent) = browser::parent_url(&url) {
+                    self.navigate_to(&parent)?;
+                }
             }
+            Some(Suggestion::Cached) => self.reload_with(&url, ReadOptions { cached: true, ..ReadOptions::default() }),
+            Some(Suggestion::Download) => self.reload_with(&url, ReadOptions { any_size: true, ..ReadOptions::default() }),
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Load `url` again with `options`, in place if it's the current page.
+    fn reload_with(&mut self, url: &str, options: ReadOptions) {
+        let visit = if url == self.tab().browser.current_url { Visit::Reload } else { Visit::Forward };
+        self.start_load_with(url, Destination::Tab(self.active, visit), options);
+    }
+
+    pub fn select(&mut self) -> Result<()> {
+        if let Some(entry) = self.tab().selected_entry() {
+            let url = entry.url.clone();
+            self.navigate_to(&url)?;
         }
         Ok(())
     }
 
     pub fn go_back(&mut self) -> Result<()> {
-        if self.mode == Mode::Viewer {
-            // Return to directory
-            match self.browser.go_back() {
-                Ok(Some(page)) => self.apply_page(page),
-                Ok(None) => {}
-                Err(e) => self.error = Some(e.to_string()),
-            }
-        } else if let Ok(Some(page)) = self.browser.go_back() {
-            self.apply_page(page);
+        if let Some(url) = self.tab().browser.previous().map(String::from) {
+            self.start_load(&url, Destination::Tab(self.active, Visit::Back));
         }
         Ok(())
     }
 
     pub fn refresh(&mut self) -> Result<()> {
-        match self.browser.refresh() {
-            Ok(page) => self.apply_page(page),
-            Err(e) => self.error = Some(e.to_string()),
-        }
+        let url = self.tab().browser.current_url.clone();
+        self.start_load(&url, Destination::Tab(self.active, Visit::Reload));
         Ok(())
     }
 
-    pub fn next(&mut self) {
-        if self.entries.is_empty() { return; }
-        let i = self.list_state.selected().map(|i| (i + 1).min(self.entries.len() - 1)).unwrap_or(0);
-        if self.list_state.selected() != Some(i) {
-            self.marquee_offset = 0;
+    /// Open the focused link: textfiles.com pages in the browser, anything
+    /// else is left to the terminal's own hyperlink handling.
+    pub fn follow_link(&mut self) -> Result<()> {
+        let Some(link) = self.tab().current_link() else {
+            return Ok(());
+        };
+        let url = link.url.clone();
+        if link.kind == LinkKind::Textfiles {
+            self.navigate_to(&url)?;
+        } else {
+            self.flash(format!("External link: {url}"));
         }
-        self.list_state.select(Some(i));
+        Ok(())
     }
 
-    pub fn previous(&mut self) {
-        let i = self.list_state.selected().map(|i| i.saturating_sub(1)).unwrap_or(0);
-        if self.list_state.selected() != Some(i) {
-            self.marquee_offset = 0;
+    /// List the Wayback captures of the current page to pick from.
+    pub fn open_snapshots(&mut self) {
+        let current = self.tab().browser.current_url.clone();
+        self.snapshots_of(&current);
+    }
+
+    /// List the captures of `current`, or of the page it's a capture of,
+    /// in the background.
+    fn snapshots_of(&mut self, current: &str) {
+        if !wayback::original(current).starts_with("http") {
+            self.flash("Only web pages have Wayback snapshots".to_string());
+            return;
         }
-        self.list_state.select(Some(i));
+        self.start_load_with(current, Destination::Snapshots(self.active), ReadOptions::default());
     }
 
-    pub fn page_down(&mut self) {
-        if self.entries.is_empty() { return; }
-        let i = self.list_state.selected().map(|i| (i + 20).min(self.entries.len() - 1)).unwrap_or(0);
-        if self.list_state.selected() != Some(i) {
-            self.marquee_offset = 0;
+    /// Open the picker on `snapshots` of `current`.
+    fn pick_snapshot(&mut self, current: &str, mut snapshots: Vec<String>) {
+        let url = wayback::original(current).to_string();
+        if snapshots.is_empty() {
+            self.flash(format!("No Wayback snapshots of {url}"));
+            return;
         }
-        self.list_state.select(Some(i));
+        snapshots.reverse();
+        // Start on the snapshot being viewed, or the live site
+        let selected = wayback::parse(current)
+            .and_then(|s| snapshots.iter().position(|t| t == s.timestamp))
+            .map_or(0, |i| i + 1);
+        let state = ListState::default().with_selected(Some(selected));
+        self.snapshot_picker = Some(SnapshotPicker { url, snapshots, state });
     }
 
-    pub fn page_up(&mut self) {
-        let i = self.list_state.selected().map(|i| i.saturating_sub(20)).unwrap_or(0);
-        if self.list_state.selected() != Some(i) {
-            self.marquee_offset = 0;
+    fn picker_key(&mut self, key: KeyEvent) -> Result<()> {
+        let page_size = self.page_size;
+        let Some(picker) = self.snapshot_picker.as_mut() else {
+            return Ok(());
+        };
+        let last = picker.snapshots.len();
+        let i = picker.state.selected().unwrap_or(0);
+        let i = match key.code {
+            KeyCode::Up | KeyCode::Char('k') => i.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => (i + 1).min(last),
+            KeyCode::PageUp => i.saturating_sub(page_size),
+            KeyCode::PageDown => (i + page_size).min(last),
+            KeyCode::Home | KeyCode::Char('g') => 0,
+            KeyCode::End | KeyCode::Char('G') => last,
+            KeyCode::Enter => {
+                let url = match i {
+                    0 => picker.url.clone(),
+                    i => self.tabs[self.active].browser.wayback.url(&picker.url, &picker.snapshots[i - 1]),
+                };
+                self.snapshot_picker = None;
+                return self.navigate_to(&url);
+            }
+            _ => {
+                self.snapshot_picker = None;
+                return Ok(());
+            }
+        };
+        picker.state.select(Some(i));
+        Ok(())
+    }
+
+    /// Open the selected entry in a new tab after the current one.
+    pub fn open_in_new_tab(&mut self) -> Result<()> {
+        let Some(url) = self.tab().selected_entry().map(|e| e.url.clone()) else {
+            return Ok(());
+        };
+        self.start_load(&url, Destination::NewTab(self.active));
+        Ok(())
+    }
+
+    /// Close the active tab, cancelling its loads, including any tabs it
+    /// was about to open.
+    pub fn close_tab(&mut self) {
+        if self.tabs.len() > 1 {
+            let closed = self.active;
+            self.tabs.remove(closed);
+            self.renumber_loads(|i| match i.cmp(&closed) {
+                Ordering::Less => Some(i),
+                Ordering::Equal => None,
+                Ordering::Greater => Some(i - 1),
+            });
+            self.switch_tab(self.active.min(self.tabs.len() - 1));
         }
-        self.list_state.select(Some(i));
     }
 
-    pub fn home(&mut self) {
-        if !self.entries.is_empty() {
-            if self.list_state.selected() != Some(0) {
-                self.marquee_offset = 0;
+    /// Keep loads headed for the right tabs as tabs come and go.
+    fn renumber_loads(&mut self, renumber: impl Fn(usize) -> Option<usize>) {
+        self.loads.retain_mut(|l| match l.destination.renumber(&renumber) {
+            Some(destination) => {
+                l.destination = destination;
+                true
             }
-            self.list_state.select(Some(0));
+            None => false,
+        });
+    }
+
+    pub fn switch_tab(&mut self, i: usize) {
+        self.active = i % self.tabs.len();
+        // Width or wrap mode may have changed while the tab was hidden
+        let (width, wrap) = (self.text_width(), self.wrap_mode);
+        let tab = self.tab_mut();
+        if tab.mode == Mode::Viewer {
+            tab.rewrap(width, wrap);
+        }
+    }
+
+    /// Handle one key press: dismiss errors, resolve bindings, act.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Result<()> {
+        if self.error.is_some() {
+            return self.error_key(key);
+        }
+        if key.code == KeyCode::Esc && self.active_loads().next().is_some() {
+            let active = self.active;
+            self.loads.retain(|l| l.destination.tab() != active);
+            self.flash("Cancelled".to_string());
+            return Ok(());
+        }
+        if self.show_help {
+            self.show_help = false;
+            return Ok(());
+        }
+        if self.snapshot_picker.is_some() {
+            return self.picker_key(key);
+        }
+        if let Some((action, count)) = self.keymap.feed(key, self.mode()) {
+            self.perform(action, count)?;
         }
+        Ok(())
     }
 
-    pub fn end(&mut self) {
-        if !self.entries.is_empty() {
-            let last = self.entries.len() - 1;
-            if self.list_state.selected() != Some(last) {
-                self.marquee_offset = 0;
+    pub fn perform(&mut self, action: Action, count: Option<usize>) -> Result<()> {
+        let n = count.unwrap_or(1);
+        let visible_height = self.visible_height();
+        let page_size = self.page_size;
+        // Counts can be huge; moving further than the list is long, or round
+        // the links more than once, changes nothing
+        let entries = self.tab().entries.len();
+        let link_steps = match self.tab().links.len() {
+            0 => 0,
+            links => 1 + (n - 1) % links,
+        };
+        match (self.mode(), action) {
+            (_, Action::Quit) => self.quit = true,
+            (_, Action::Refresh) => self.refresh()?,
+            (_, Action::CycleTheme) => self.cycle_theme(),
+            (_, Action::Help) => self.show_help = true,
+            (_, Action::Snapshots) => self.open_snapshots(),
+            (_, Action::NextTab) => self.switch_tab(self.active + n % self.tabs.len()),
+            (_, Action::PrevTab) => {
+                let len = self.tabs.len();
+                self.switch_tab(self.active + len - n % len);
+            }
+            (_, Action::CloseTab) => self.close_tab(),
+            (_, Action::ToggleHeader) => self.header_collapsed = !self.header_collapsed,
+            (Mode::Browser, Action::TogglePreview) => self.preview.enabled = !self.preview.enabled,
+            (Mode::Browser, Action::OpenInNewTab) => self.open_in_new_tab()?,
+            (_, Action::Back) => self.go_back()?,
+            (Mode::Browser, Action::Up) => (0..n.min(entries)).for_each(|_| self.tab_mut().previous()),
+            (Mode::Browser, Action::Down) => (0..n.min(entries)).for_each(|_| self.tab_mut().next()),
+            (Mode::Browser, Action::PageUp) => self.tab_mut().page_up(n.saturating_mul(page_size)),
+            (Mode::Browser, Action::PageDown) => self.tab_mut().page_down(n.saturating_mul(page_size)),
+            (Mode::Browser, Action::Top) => match count {
+                Some(n) => self.tab_mut().select_index(n - 1),
+                None => self.tab_mut().home(),
+            },
+            (Mode::Browser, Action::Bottom) => match count {
+                Some(n) => self.tab_mut().select_index(n - 1),
+                None => self.tab_mut().end(),
+            },
+            (Mode::Browser, Action::Open) => self.select()?,
+            (Mode::Viewer, Action::Up) => self.tab_mut().scroll_up(n),
+            (Mode::Viewer, Action::Down) => self.tab_mut().scroll_down(n, visible_height),
+            (Mode::Viewer, Action::PageUp) => self.tab_mut().scroll_up(n.saturating_mul(page_size)),
+            (Mode::Viewer, Action::PageDown) => self.tab_mut().scroll_down(n.saturating_mul(page_size), visible_height),
+            (Mode::Viewer, Action::Top | Action::Bottom) if count.is_some() => {
+                let tab = self.tab_mut();
+                tab.scroll_home();
+                tab.scroll_down(n - 1, visible_height);
             }
-            self.list_state.select(Some(last));
+            (Mode::Viewer, Action::Top) => self.tab_mut().scroll_home(),
+            (Mode::Viewer, Action::Bottom) => self.tab_mut().scroll_end(visible_height),
+            (Mode::Viewer, Action::ToggleWrap) => self.toggle_wrap(),
+            (Mode::Viewer, Action::NextLink) => (0..link_steps).for_each(|_| self.tab_mut().focus_link(true, visible_height)),
+            (Mode::Viewer, Action::PrevLink) => (0..link_steps).for_each(|_| self.tab_mut().focus_link(false, visible_height)),
+            (Mode::Viewer, Action::FollowLink) => self.follow_link()?,
+            (_, Action::ToggleWrap | Action::TogglePreview | Action::Open | Action::OpenInNewTab
+                | Action::NextLink | Action::PrevLink | Action::FollowLink) => {}
         }
+        Ok(())
     }
 
-    pub fn scroll_up(&mut self, n: usize) {
-        self.scroll = self.scroll.saturating_sub(n);
+    pub fn handle_mouse(&mut self, event: MouseEvent) -> Result<()> {
+        let pos = Position::new(event.column, event.row);
+        let visible_height = self.visible_height();
+        if self.error.is_some() || self.show_help || self.snapshot_picker.is_some() {
+            if matches!(event.kind, MouseEventKind::Down(_)) {
+                self.error = None;
+                self.show_help = false;
+                self.snapshot_picker = None;
+            }
+            return Ok(());
+        }
+
+        match event.kind {
+            MouseEventKind::ScrollUp => match self.mode() {
+                Mode::Browser => self.tab_mut().page_up(WHEEL_STEP),
+                Mode::Viewer => self.tab_mut().scroll_up(WHEEL_STEP),
+            },
+            MouseEventKind::ScrollDown => match self.mode() {
+                Mode::Browser => self.tab_mut().page_down(WHEEL_STEP),
+                Mode::Viewer => self.tab_mut().scroll_down(WHEEL_STEP, visible_height),
+            },
+            MouseEventKind::Down(MouseButton::Left) => {
+                let target = self.targets.iter()
+                    .find(|(rect, _)| rect.contains(pos))
+                    .map(|(_, target)| target.clone());
+                match target {
+                    Some(Target::Back) => self.go_back()?,
+                    Some(Target::Help) => self.show_help = true,
+                    Some(Target::Url(url)) => self.navigate_to(&url)?,
+                    Some(Target::Tab(i)) => self.switch_tab(i),
+                    Some(Target::Link(i)) => {
+                        self.tab_mut().focused_link = Some(i);
+                        self.follow_link()?;
+                    }
+                    None if self.on_scrollbar(pos) => {
+                        self.dragging_scrollbar = true;
+                        self.drag_scrollbar(event.row, visible_height);
+                    }
+                    None => self.click_entry(pos)?,
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.dragging_scrollbar => {
+                self.drag_scrollbar(event.row, visible_height);
+            }
+            MouseEventKind::Up(_) => self.dragging_scrollbar = false,
+            _ => {}
+        }
+        Ok(())
     }
 
-    pub fn scroll_down(&mut self, n: usize, visible_height: usize) {
-        let max_scroll = self.wrapped_lines.len().saturating_sub(visible_height);
-        self.scroll = (self.scroll + n).min(max_scroll);
+    /// Select the clicked entry; open it on a double click.
+    fn click_entry(&mut self, pos: Position) -> Result<()> {
+        let area = self.content_area;
+        let inside = pos.x > area.x && pos.x + 1 < area.right()
+            && pos.y > area.y && pos.y + 1 < area.bottom();
+        if self.mode() != Mode::Browser || !inside {
+            return Ok(());
+        }
+        let i = self.tab().list_state.offset() + (pos.y - area.y - 1) as usize;
+        if i >= self.tab().entries.len() {
+            return Ok(());
+        }
+        let double = self.last_click.is_some_and(|(at, j)| j == i && at.elapsed() < DOUBLE_CLICK);
+        self.tab_mut().select_index(i);
+        if double {
+            self.last_click = None;
+            self.select()?;
+        } else {
+            self.last_click = Some((Instant::now(), i));
+        }
+        Ok(())
     }
 
-    pub fn scroll_home(&mut self) {
-        self.scroll = 0;
+    fn on_scrollbar(&self, pos: Position) -> bool {
+        let area = self.content_area;
+        pos.x + 1 == area.right() && pos.y > area.y && pos.y + 1 < area.bottom()
     }
 
-    pub fn scroll_end(&mut self, visible_height: usize) {
-        self.scroll = self.wrapped_lines.len().saturating_sub(visible_height);
+    /// Move so the scrollbar thumb sits at `row`.
+    fn drag_scrollbar(&mut self, row: u16, visible_height: usize) {
+        let area = self.content_area;
+        let track = area.height.saturating_sub(3).max(1) as usize;
+        let offset = row.saturating_sub(area.y + 1).min(track as u16) as usize;
+        let tab = self.tab_mut();
+        match tab.mode {
+            Mode::Browser => {
+                let last = tab.entries.len().saturating_sub(1);
+                tab.select_index(offset * last / track);
+            }
+            Mode::Viewer => {
+                let max_scroll = tab.line_count().saturating_sub(visible_height);
+                tab.scroll = offset * max_scroll / track;
+            }
+        }
     }
 
-    pub fn rewrap_lines(&mut self) {
-        let width = self.view_width.saturating_sub(3) as usize; // borders + scrollbar
-        let width = width.max(40); // minimum sanity
-        self.wrapped_lines = self.text_lines.iter()
-            .flat_map(|line| wrap_line(line, width))
-            .collect();
+    pub fn toggle_wrap(&mut self) {
+        self.wrap_mode = match self.wrap_mode {
+            WrapMode::Hard => WrapMode::Reflow,
+            WrapMode::Reflow => WrapMode::Hard,
+        };
+        // Keep roughly the same place in the file
+        let (width, wrap) = (self.text_width(), self.wrap_mode);
+        let tab = self.tab_mut();
+        let old_total = tab.line_count().max(1);
+        tab.rewrap(width, wrap);
+        tab.scroll = tab.scroll * tab.line_count() / old_total;
     }
 
     pub fn update_view_width(&mut self, new_width: u16) {
         if new_width != self.view_width {
             self.view_width = new_width;
-            if self.mode == Mode::Viewer && !self.text_lines.is_empty() {
-                self.rewrap_lines();
+            let (width, wrap) = (self.text_width(), self.wrap_mode);
+            let tab = self.tab_mut();
+            if tab.mode == Mode::Viewer {
+                tab.rewrap(width, wrap);
             }
         }
     }
 
+    pub fn cycle_theme(&mut self) {
+        let i = self.themes.iter().position(|t| t.name == self.theme.name).unwrap_or(0);
+        self.theme = self.themes[(i + 1) % self.themes.len()].clone();
+        self.flash(format!("Theme: {}", self.theme.name));
+    }
+
+    /// Show a short-lived message in the status bar.
+    pub fn flash(&mut self, msg: String) {
+        self.flash = Some((msg, self.tick + FLASH_TICKS));
+    }
+
     pub fn tick(&mut self) {
+        self.poll_loads();
         self.tick = self.tick.wrapping_add(1);
         // Advance marquee every 4 ticks (~200ms at 50ms poll)
-        if self.tick % 4 == 0 {
+        if self.tick.is_multiple_of(4) {
             self.marquee_offset = self.marquee_offset.wrapping_add(1);
         }
+        // Restart it whenever the text it scrolls changes
+        let (tab, url, selected) = &self.marquee_anchor;
+        if *tab != self.active || *url != self.tab().browser.current_url || *selected != self.tab().list_state.selected() {
+            self.marquee_anchor = (self.active, self.tab().browser.current_url.clone(), self.tab().list_state.selected());
+            self.marquee_offset = 0;
+        }
+        if self.flash.as_ref().is_some_and(|(_, until)| self.tick >= *until) {
+            self.flash = None;
+        }
+        if self.mode() == Mode::Browser
+            && let Some(entry) = self.tab().selected_entry()
+        {
+            let url = entry.url.clone();
+            self.preview.want(&url, self.tick);
+        }
+        self.preview.tick(self.tick);
     }
 }
 
 pub fn draw(frame: &mut Frame, app: &mut App) {
+    app.targets.clear();
+    app.hyperlinks.clear();
+    let area = frame.area();
+    let art_width = HEADER.lines().map(|l| l.chars().count()).max().unwrap_or(0) as u16;
+    let compact = app.header_collapsed
+        || area.height < FULL_HEADER_MIN_HEIGHT
+        || area.width < art_width;
+    let header_height = if compact { 1 } else { HEADER.lines().count() as u16 + 1 };
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(12),
+            Constraint::Length(header_height),
+            Constraint::Length(1),
             Constraint::Min(5),
             Constraint::Length(2),
         ])
-        .split(frame.area());
+        .split(area);
 
-    draw_header(frame, chunks[0], app);
+    if compact {
+        draw_banner(frame, chunks[0], app);
+    } else {
+        draw_header(frame, chunks[0], app);
+    }
+    draw_tabs(frame, chunks[1], app);
+    let chunks = [chunks[0], chunks[2], chunks[3]];
+    app.content_area = chunks[1];
 
-    match app.mode {
+    match app.mode() {
+        _ if downloading(app).is_some() => draw_partial(frame, chunks[1], app),
+        Mode::Browser if app.preview.enabled => {
+            let panes = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(chunks[1]);
+            app.content_area = panes[0];
+            draw_browser(frame, panes[0], app);
+            draw_preview(frame, panes[1], app);
+        }
         Mode::Browser => draw_browser(frame, chunks[1], app),
         Mode::Viewer => draw_viewer(frame, chunks[1], app),
     }
 
     draw_status(frame, chunks[2], app);
 
-    if let Some(ref err) = app.error {
-        draw_error(frame, frame.area(), err);
+    if app.show_help || app.error.is_some() || app.snapshot_picker.is_some() {
+        // Overlays cover the text, so don't paint links over them
+        app.hyperlinks.clear();
+    }
+    if app.show_help {
+        draw_help(frame, frame.area(), app);
+    }
+
+    if app.snapshot_picker.is_some() {
+        draw_snapshots(frame, frame.area(), app);
+    }
+
+    if app.error.is_some() {
+        draw_error(frame, frame.area(), app);
     }
 }
 
+/// Repaint the links from the last frame wrapped in OSC 8 escapes, so
+/// terminals that support them make the text clickable. Done outside
+/// ratatui because its cells hold one grapheme each.
+pub fn write_hyperlinks(out: &mut impl Write, links: &[Hyperlink]) -> Result<()> {
+    use crossterm::{cursor::MoveTo, queue, style::{self, Attribute, Color, Print}};
+    // Both come from the page, so nothing in them may act as an escape
+    let printable = |text: &str| text.chars().filter(|c| !c.is_control()).collect::<String>();
+    for link in links {
+        queue!(out, MoveTo(link.area.x, link.area.y))?;
+        if let Some(fg) = link.style.fg {
+            queue!(out, style::SetForegroundColor(Color::from(fg)))?;
+        }
+        if let Some(bg) = link.style.bg {
+            queue!(out, style::SetBackgroundColor(Color::from(bg)))?;
+        }
+        for (modifier, attribute) in [
+            (Modifier::BOLD, Attribute::Bold),
+            (Modifier::REVERSED, Attribute::Reverse),
+            (Modifier::UNDERLINED, Attribute::Underlined),
+        ] {
+            if link.style.add_modifier.contains(modifier) {
+                queue!(out, style::SetAttribute(attribute))?;
+            }
+        }
+        queue!(out,
+            Print(format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", printable(&link.url), printable(&link.text))),
+            style::SetAttribute(Attribute::Reset),
+            style::ResetColor,
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
 fn draw_header(frame: &mut Frame, area: Rect, app: &App) {
-    let color = if app.tick % 30 < 2 { GREEN_NORMAL } else { GREEN_BRIGHT };
+    let theme = &app.theme;
+    let color = if app.tick % 30 < 2 { theme.normal } else { theme.bright };
     let lines: Vec<Line> = HEADER.lines()
         .map(|l| Line::from(Span::styled(l, Style::default().fg(color))))
         .collect();
     let p = Paragraph::new(lines)
         .alignment(Alignment::Center)
-        .style(Style::default().bg(BLACK));
+        .style(Style::default().bg(theme.background));
+    frame.render_widget(p, area);
+}
+
+/// One-line stand-in for the header art
+fn draw_banner(frame: &mut Frame, area: Rect, app: &App) {
+    let theme = &app.theme;
+    let title = " TEXTFILES.COM ";
+    let rule = (area.width as usize).saturating_sub(title.len()) / 2;
+    let line = Line::from(vec![
+        Span::styled("═".repeat(rule), Style::default().fg(theme.dim)),
+        Span::styled(title, Style::default().fg(theme.bright).add_modifier(Modifier::BOLD)),
+        Span::styled("═".repeat(rule), Style::default().fg(theme.dim)),
+    ]);
+    let p = Paragraph::new(line)
+        .alignment(Alignment::Center)
+        .style(Style::default().bg(theme.background));
     frame.render_widget(p, area);
 }
 
+fn draw_tabs(frame: &mut Frame, area: Rect, app: &mut App) {
+    let theme = app.theme.clone();
+    // Share the width out evenly, but don't let one tab hog a wide screen
+    let slot = (area.width as usize / app.tabs.len()).clamp(6, 24);
+
+    let mut spans = Vec::new();
+    let mut x = area.x;
+    for (i, tab) in app.tabs.iter().enumerate() {
+        if x >= area.right() {
+            break;
+        }
+        let label: String = format!(" {}:{}", i + 1, tab.label()).chars().take(slot - 1).collect();
+        let text = format!("{label:<w$} ", w = slot - 1);
+        let style = if i == app.active {
+            theme.selection().add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(theme.dim)
+        };
+        let width = (slot as u16).min(area.right() - x);
+        app.targets.push((Rect::new(x, area.y, width, 1), Target::Tab(i)));
+        spans.push(Span::styled(text, style));
+        x += slot as u16;
+    }
+
+    frame.render_widget(Paragraph::new(Line::from(spans)).style(Style::default().bg(theme.background)), area);
+}
+
 fn draw_browser(frame: &mut Frame, area: Rect, app: &mut App) {
+    let theme = &app.theme;
     let content_width = area.width.saturating_sub(5) as usize; // borders + scrollbar + highlight
 
-    let items: Vec<ListItem> = app.entries.iter().enumerate().map(|(i, e)| {
-        let selected = app.list_state.selected() == Some(i);
-        let icon = if e.is_dir { "<DIR>" } else { "     " };
+    let tab = &app.tabs[app.active];
+    let items: Vec<ListItem> = tab.entries.iter().enumerate().map(|(i, e)| {
+        let selected = tab.list_state.selected() == Some(i);
+        let icon = match (e.is_dir, e.size) {
+            (true, _) => "<DIR>".to_string(),
+            (false, Some(size)) => format!("{:>5}", binary::short_size(size)),
+            (false, None) => "     ".to_string(),
+        };
 
         let (name_style, desc_style) = if selected {
-            (Style::default().fg(BLACK).bg(GREEN_BRIGHT).add_modifier(Modifier::BOLD),
-             Style::default().fg(BLACK).bg(GREEN_BRIGHT))
+            (theme.selection().add_modifier(Modifier::BOLD),
+             theme.selection())
         } else if e.is_dir {
-            (Style::default().fg(GREEN_BRIGHT),
-             Style::default().fg(GREEN_DIM))
+            (Style::default().fg(theme.bright),
+             Style::default().fg(theme.dim))
         } else {
-            (Style::default().fg(GREEN_NORMAL),
-             Style::default().fg(GREEN_DIM))
+            (Style::default().fg(theme.normal),
+             Style::default().fg(theme.dim))
         };
 
         let name_part = format!("{} {}", icon, e.name);
@@ -352,64 +943,207 @@ fn draw_browser(frame: &mut Frame, area: Rect, app: &mut App) {
         .block(Block::default()
             .borders(Borders::ALL)
             .border_set(border::PLAIN)
-            .border_style(Style::default().fg(GREEN_DIM))
+            .border_style(Style::default().fg(theme.dim))
             .title(Span::styled(
-                format!(" {} ", app.title),
-                Style::default().fg(GREEN_BRIGHT).add_modifier(Modifier::BOLD)
+                format!(" {} ", tab.title),
+                Style::default().fg(theme.bright).add_modifier(Modifier::BOLD)
             ))
-            .style(Style::default().bg(BLACK)))
+            .style(Style::default().bg(theme.background)))
         .highlight_symbol("> ");
 
-    let mut scrollbar_state = ScrollbarState::new(app.entries.len())
-        .position(app.list_state.selected().unwrap_or(0));
+    let mut scrollbar_state = ScrollbarState::new(tab.entries.len())
+        .position(tab.list_state.selected().unwrap_or(0));
 
-    frame.render_stateful_widget(list, area, &mut app.list_state);
+    frame.render_stateful_widget(list, area, &mut app.tabs[app.active].list_state);
     frame.render_stateful_widget(
         Scrollbar::new(ScrollbarOrientation::VerticalRight)
-            .style(Style::default().fg(GREEN_DIM)),
+            .style(Style::default().fg(theme.dim)),
         area,
         &mut scrollbar_state
     );
 }
 
-fn draw_viewer(frame: &mut Frame, area: Rect, app: &App) {
+fn draw_viewer(frame: &mut Frame, area: Rect, app: &mut App) {
+    let theme = &app.theme;
+    let tab = &app.tabs[app.active];
     let height = area.height.saturating_sub(2) as usize;
+    let text_style = Style::default().fg(theme.normal);
+    let link_style = Style::default().fg(theme.bright).add_modifier(Modifier::UNDERLINED);
 
-    let lines: Vec<Line> = app.wrapped_lines.iter()
-        .skip(app.scroll)
-        .take(height)
-        .map(|l| Line::from(Span::styled(l.as_str(), Style::default().fg(GREEN_NORMAL))))
-        .collect();
+    let mut lines = Vec::new();
+    for (row, line) in tab.lines(tab.scroll, height).iter().enumerate() {
+        let i = tab.scroll + row;
+        let chars: Vec<char> = line.chars().collect();
+        let mut spans = Vec::new();
+        let mut col = 0;
+        for (n, link) in tab.links.iter().enumerate().filter(|(_, l)| l.line == i) {
+            let len = link.text.chars().count();
+            let before: String = chars[col..link.col].iter().collect();
+            let x = area.x + 1 + Span::raw(line.chars().take(link.col).collect::<String>()).width() as u16;
+            let rect = Rect::new(x, area.y + 1 + row as u16, Span::raw(link.text.as_str()).width() as u16, 1);
+            let style = if tab.focused_link == Some(n) { theme.selection() } else { link_style };
+            spans.push(Span::styled(before, text_style));
+            spans.push(Span::styled(link.text.clone(), style));
+            app.targets.push((rect, Target::Link(n)));
+            if link.kind != LinkKind::Textfiles {
+                app.hyperlinks.push(Hyperlink { area: rect, url: link.url.clone(), text: link.text.clone(), style });
+            }
+            col = link.col + len;
+        }
+        spans.push(Span::styled(chars[col..].iter().collect::<String>(), text_style));
+        lines.push(Line::from(spans));
+    }
 
-    let total = app.wrapped_lines.len();
-    let pct = if total > 0 { ((app.scroll + height).min(total) * 100) / total } else { 100 };
+    let total = tab.line_count();
+    let pct = ((tab.scroll + height).min(total) * 100).checked_div(total).unwrap_or(100);
+    let info = match &tab.body {
+        Body::Binary(data) => format!(" ({}, {})", data.kind, binary::format_size(data.bytes.len() as u64)),
+        _ => String::new(),
+    };
 
     let p = Paragraph::new(lines)
         .block(Block::default()
             .borders(Borders::ALL)
             .border_set(border::PLAIN)
-            .border_style(Style::default().fg(GREEN_DIM))
+            .border_style(Style::default().fg(theme.dim))
             .title(Span::styled(
-                format!(" {} [{}%] ", app.title, pct),
-                Style::default().fg(GREEN_BRIGHT).add_modifier(Modifier::BOLD)
+                format!(" {}{} [{}%] ", tab.title, info, pct),
+                Style::default().fg(theme.bright).add_modifier(Modifier::BOLD)
             ))
-            .style(Style::default().bg(BLACK)));
+            .style(Style::default().bg(theme.background)));
 
-    let mut scrollbar_state = ScrollbarState::new(total).position(app.scroll);
+    let mut scrollbar_state = ScrollbarState::new(total).position(tab.scroll);
 
     frame.render_widget(p, area);
     frame.render_stateful_widget(
         Scrollbar::new(ScrollbarOrientation::VerticalRight)
-            .style(Style::default().fg(GREEN_DIM)),
+            .style(Style::default().fg(theme.dim)),
         area,
         &mut scrollbar_state
     );
 }
 
-fn draw_status(frame: &mut Frame, area: Rect, app: &App) {
-    let mode = if app.mode == Mode::Browser { "BROWSE" } else { "VIEW" };
-    let back = if app.browser.can_go_back() { "<-BACK " } else { "" };
-    let right = format!("{}q:quit ", back);
+/// The load of a text file into the current tab, once some has arrived
+fn downloading(app: &App) -> Option<&Loading> {
+    app.active_loads().find(|l| matches!(l.destination, Destination::Tab(..)) && l.head.is_some())
+}
+
+/// The viewer for a file that's still arriving: the lines so far, without
+/// scrolling or links until it's all there.
+fn draw_partial(frame: &mut Frame, area: Rect, app: &App) {
+    let theme = &app.theme;
+    let Some(loading) = downloading(app) else {
+        return;
+    };
+    let text = loading.head.as_deref().unwrap_or_default();
+    let height = area.height.saturating_sub(2) as usize;
+    let lines: Vec<Line> = text.lines()
+        .flat_map(|line| reflow::wrap_line(line, app.text_width()))
+        .take(height)
+        .map(|line| Line::from(Span::styled(line, Style::default().fg(theme.normal))))
+        .collect();
+
+    let p = Paragraph::new(lines)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_set(border::PLAIN)
+            .border_style(Style::default().fg(theme.dim))
+            .title(Span::styled(
+                format!(" {} [downloading] ", file_name(&loading.url)),
+                Style::default().fg(theme.bright).add_modifier(Modifier::BOLD)
+            ))
+            .style(Style::default().bg(theme.background)));
+    frame.render_widget(p, area);
+}
+
+fn draw_preview(frame: &mut Frame, area: Rect, app: &App) {
+    let theme = &app.theme;
+    let height = area.height.saturating_sub(2) as usize;
+    let width = area.width.saturating_sub(2) as usize;
+    let entry = app.tab().selected_entry();
+
+    let dim = Style::default().fg(theme.dim);
+    let (title, lines): (String, Vec<Line>) = match entry.map(|e| (e, app.preview.get(&e.url))) {
+        None => (String::new(), Vec::new()),
+        Some((e, None | Some(PreviewState::Loading))) => {
+            (e.name.clone(), vec![Line::from(Span::styled("Loading...", dim))])
+        }
+        Some((e, Some(PreviewState::Failed(err)))) => {
+            (e.name.clone(), vec![Line::from(Span::styled(err.as_str(), Style::default().fg(theme.alert)))])
+        }
+        Some((e, Some(PreviewState::Ready(page)))) => {
+            let lines = match &page.content {
+                Content::Directory(entries) => entries.iter()
+                    .take(height)
+                    .map(|e| {
+                        let (icon, style) = if e.is_dir {
+                            ("<DIR>", Style::default().fg(theme.bright))
+                        } else {
+                            ("     ", Style::default().fg(theme.normal))
+                        };
+                        let text: String = format!("{} {}", icon, e.name).chars().take(width).collect();
+                        Line::from(Span::styled(text, style))
+                    })
+                    .collect(),
+                Content::TextFile(text) => text.lines()
+                    .take(height)
+                    .map(|l| {
+                        let l: String = l.chars().take(width).collect();
+                        Line::from(Span::styled(l, Style::default().fg(theme.normal)))
+                    })
+                    .collect(),
+                Content::Document(document) => document.render(width)
+                    .into_iter()
+                    .take(height)
+                    .map(|l| Line::from(Span::styled(l, Style::default().fg(theme.normal))))
+                    .collect(),
+                Content::Binary(data) => {
+                    // Only the start was read, so the size comes from the listing
+                    let info = match e.size {
+                        Some(size) => format!("{}, {}", data.kind, binary::format_size(size)),
+                        None => data.kind.to_string(),
+                    };
+                    std::iter::once(Line::from(Span::styled(info, Style::default().fg(theme.bright))))
+                        .chain((0..binary::dump_rows(&data.bytes, binary::row_len(width)))
+                            .map(|row| binary::dump_row(&data.bytes, row, binary::row_len(width)))
+                            .map(|l| Line::from(Span::styled(l, Style::default().fg(theme.normal)))))
+                        .take(height)
+                        .collect()
+                }
+            };
+            (page.title.clone(), lines)
+        }
+    };
+
+    let p = Paragraph::new(lines)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_set(border::PLAIN)
+            .border_style(Style::default().fg(theme.dim))
+            .title(Span::styled(
+                format!(" {} ", title),
+                Style::default().fg(theme.normal)
+            ))
+            .style(Style::default().bg(theme.background)));
+
+    frame.render_widget(p, area);
+}
+
+fn draw_status(frame: &mut Frame, area: Rect, app: &mut App) {
+    let theme = app.theme.clone();
+    let mode = match (app.mode(), &app.tab().body, app.wrap_mode) {
+        (Mode::Browser, ..) => "BROWSE",
+        (Mode::Viewer, Body::Binary(_), _) => "HEX",
+        (Mode::Viewer, _, WrapMode::Hard) => "VIEW",
+        (Mode::Viewer, _, WrapMode::Reflow) => "VIEW/REFLOW",
+    };
+    let back = if app.tab().browser.can_go_back() { "<-BACK " } else { "" };
+    let pending = app.keymap.pending();
+    let pending = if pending.is_empty() { pending } else { format!("{pending} ") };
+    let help = app.keymap.hint(Action::Help, app.mode())
+        .map(|k| format!("{k}:help "))
+        .unwrap_or_default();
+    let right = format!("{}{}{}", pending, back, help);
 
     let width = area.width as usize;
     let prefix = format!(" {} | ", mode);
@@ -417,46 +1151,465 @@ fn draw_status(frame: &mut Frame, area: Rect, app: &App) {
     let prefix_len = prefix.chars().count();
     let url_max = width.saturating_sub(prefix_len).saturating_sub(right_len).saturating_sub(1);
 
-    let url = marquee(&app.browser.current_url, url_max, app.marquee_offset);
+    let mut spans = vec![Span::styled(prefix, Style::default().fg(theme.bright))];
+    let mut x = area.x + prefix_len as u16;
+    let url = app.tab().browser.current_url.clone();
+    let home_url = app.tab().browser.home_url.clone();
+    let crumbs = breadcrumbs(&url, &home_url);
+    let message = app.active_loads().last().map(loading_message).or_else(|| app.flash.as_ref().map(|(msg, _)| msg.clone()));
+    match message {
+        Some(msg) => spans.push(Span::styled(
+            msg.chars().take(url_max).collect::<String>(),
+            Style::default().fg(theme.bright),
+        )),
+        None if crumbs.iter().map(|(text, _)| text.chars().count()).sum::<usize>() <= url_max => {
+            // Each path segment links to that ancestor directory
+            for (text, target) in crumbs {
+                let len = text.chars().count() as u16;
+                if let Some(target) = target {
+                    app.targets.push((Rect::new(x, area.y, len, 1), Target::Url(target)));
+                }
+                spans.push(Span::styled(text, Style::default().fg(theme.bright)));
+                x += len;
+            }
+        }
+        None => spans.push(Span::styled(
+            marquee(&url, url_max, app.marquee_offset),
+            Style::default().fg(theme.bright),
+        )),
+    }
 
-    let left = format!("{}{}", prefix, url);
-    let left_len = left.chars().count();
+    let left_len: usize = spans.iter().map(|s| s.content.chars().count()).sum();
     let pad = width.saturating_sub(left_len).saturating_sub(right_len);
+    spans.push(Span::raw(" ".repeat(pad)));
 
-    let line = Line::from(vec![
-        Span::styled(&left, Style::default().fg(GREEN_BRIGHT)),
-        Span::raw(" ".repeat(pad)),
-        Span::styled(&right, Style::default().fg(GREEN_DIM)),
-    ]);
+    let mut x = area.x + (left_len + pad) as u16;
+    for (text, target) in [(pending, None), (back.to_string(), Some(Target::Back)), (help, Some(Target::Help))] {
+        let len = text.chars().count() as u16;
+        if let Some(target) = target.filter(|_| len > 0) {
+            app.targets.push((Rect::new(x, area.y, len, 1), target));
+        }
+        spans.push(Span::styled(text, Style::default().fg(theme.dim)));
+        x += len;
+    }
+
+    frame.render_widget(Paragraph::new(Line::from(spans)).style(Style::default().bg(theme.background)), area);
+}
+
+/// What a load in progress is up to, for the status bar.
+fn loading_message(loading: &Loading) -> String {
+    match &loading.progress {
+        None | Some(Progress::Head { .. }) if matches!(loading.destination, Destination::Snapshots(_)) => {
+            format!("Listing snapshots of {}...", wayback::original(&loading.url))
+        }
+        None | Some(Progress::Head { .. }) => format!("Loading {}...", loading.url),
+        Some(Progress::Retrying { attempt, of, wait, reason }) => {
+            format!("{reason}; retrying in {}s ({}/{of})", wait.as_secs_f32().ceil(), attempt + 1)
+        }
+        Some(Progress::Downloading { received, total }) => download_message(&file_name(&loading.url), *received, *total),
+        Some(Progress::Failover { reason, to }) => format!("{reason}; trying {to}"),
+    }
+}
+
+/// `Downloading name`, then a bar and percentage if the size is known or
+/// just the bytes so far if not.
+fn download_message(name: &str, received: u64, total: Option<u64>) -> String {
+    match total.filter(|&t| t > 0) {
+        Some(total) => {
+            let filled = (received * PROGRESS_WIDTH / total).min(PROGRESS_WIDTH) as usize;
+            format!(
+                "Downloading {name} {}{} {}% of {}",
+                "█".repeat(filled),
+                "░".repeat(PROGRESS_WIDTH as usize - filled),
+                (received * 100 / total).min(100),
+                binary::format_size(total),
+            )
+        }
+        None => format!("Downloading {name}: {}", binary::format_size(received)),
+    }
+}
 
-    frame.render_widget(Paragraph::new(line).style(Style::default().bg(BLACK)), area);
+fn file_name(url: &str) -> String {
+    local::decode(url.trim_end_matches('/').rsplit('/').next().unwrap_or(url))
 }
 
-fn draw_error(frame: &mut Frame, area: Rect, msg: &str) {
+/// Split a URL into display pieces, pairing path segments with the URL
+/// of the directory they name. The host links to the home page.
+fn breadcrumbs(url: &str, home_url: &str) -> Vec<(String, Option<String>)> {
+    // Snapshots show the original path, with ancestors at the same time
+    if let Some(snapshot) = wayback::parse(url) {
+        let prefix = &url[..url.len() - snapshot.original.len()];
+        let date: String = wayback::format_timestamp(snapshot.timestamp).chars().take(10).collect();
+        let mut crumbs = vec![(format!("@{date} "), None)];
+        crumbs.extend(breadcrumbs(snapshot.original, home_url).into_iter().map(|(text, target)| {
+            (text, target.map(|t| if t.starts_with("http") { format!("{prefix}{t}") } else { t }))
+        }));
+        return crumbs;
+    }
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return vec![(url.to_string(), None)];
+    };
+    let mut parts = rest.split('/');
+    let host = parts.next().unwrap_or_default();
+    let segments: Vec<&str> = parts.collect();
+
+    let mut crumbs = vec![
+        (format!("{scheme}://"), None),
+        (host.to_string(), Some(home_url.to_string())),
+    ];
+    let mut dir = format!("{scheme}://{host}/");
+    for (i, segment) in segments.iter().enumerate() {
+        crumbs.push(("/".to_string(), None));
+        if segment.is_empty() {
+            continue;
+        }
+        let is_last = segments[i + 1..].iter().all(|s| s.is_empty());
+        dir.push_str(segment);
+        dir.push('/');
+        let target = if is_last { None } else { Some(dir.clone()) };
+        crumbs.push((local::decode(segment), target));
+    }
+    crumbs
+}
+
+fn draw_error(frame: &mut Frame, area: Rect, app: &App) {
+    let theme = &app.theme;
+    let Some(view) = &app.error else {
+        return;
+    };
     let w = 60.min(area.width.saturating_sub(4));
-    let h = 7;
+    let text_width = w.saturating_sub(4) as usize;
+    let wrap = |text: &str| {
+        let words: Vec<String> = text.split_whitespace().map(String::from).collect();
+        reflow::fill(&words, 0, 0, text_width)
+    };
+
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(Span::styled("ERROR", Style::default().fg(theme.alert).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+    ];
+    lines.extend(wrap(&view.error.to_string()).into_iter()
+        .map(|l| Line::from(Span::styled(l, Style::default().fg(theme.bright)))));
+    lines.push(Line::from(""));
+    if view.details {
+        // Everything under the summary, most general first
+        lines.extend(wrap(&view.url).into_iter().map(|l| Line::from(Span::styled(l, Style::default().fg(theme.dim)))));
+        for cause in view.error.chain().skip(1) {
+            for (i, l) in wrap(&cause.to_string()).into_iter().enumerate() {
+                let l = if i == 0 { format!("- {l}") } else { format!("  {l}") };
+                lines.push(Line::from(Span::styled(l, Style::default().fg(theme.normal))).alignment(Alignment::Left));
+            }
+        }
+        lines.push(Line::from(""));
+    } else if let Some(e) = error::classify(&view.error) {
+        lines.extend(wrap(e.explanation()).into_iter()
+            .map(|l| Line::from(Span::styled(l, Style::default().fg(theme.normal)))));
+        lines.push(Line::from(""));
+    }
+    let actions: Vec<String> = app.suggestions().iter().map(|s| format!("{}:{}", s.key(), s.label())).collect();
+    if !actions.is_empty() {
+        lines.push(Line::from(Span::styled(actions.join("  "), Style::default().fg(theme.bright))));
+    }
+    let details = if view.details { "d:summary" } else { "d:details" };
+    lines.push(Line::from(Span::styled(format!("{details}  any other key closes"), Style::default().fg(theme.dim))));
+
+    let h = (lines.len() as u16 + 2).min(area.height);
     let x = (area.width - w) / 2;
     let y = (area.height - h) / 2;
     let rect = Rect::new(x, y, w, h);
 
     frame.render_widget(Clear, rect);
 
-    let lines = vec![
-        Line::from(""),
-        Line::from(Span::styled("ERROR", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))),
-        Line::from(""),
-        Line::from(Span::styled(msg, Style::default().fg(GREEN_BRIGHT))),
-        Line::from(""),
-        Line::from(Span::styled("Press any key...", Style::default().fg(GREEN_DIM))),
-    ];
-
     let p = Paragraph::new(lines)
         .alignment(Alignment::Center)
         .block(Block::default()
             .borders(Borders::ALL)
             .border_set(border::PLAIN)
-            .border_style(Style::default().fg(Color::Red))
-            .style(Style::default().bg(BLACK)));
+            .border_style(Style::default().fg(theme.alert))
+            .padding(Padding::horizontal(1))
+            .style(Style::default().bg(theme.background)));
+
+    frame.render_widget(p, rect);
+}
+
+fn draw_snapshots(frame: &mut Frame, area: Rect, app: &mut App) {
+    let theme = &app.theme;
+    let Some(picker) = app.snapshot_picker.as_mut() else {
+        return;
+    };
+    let items: Vec<ListItem> = std::iter::once("Live site".to_string())
+        .chain(picker.snapshots.iter().map(|ts| wayback::format_timestamp(ts)))
+        .map(|text| ListItem::new(Span::styled(text, Style::default().fg(theme.normal))))
+        .collect();
+
+    let w = 40.min(area.width.saturating_sub(4));
+    let h = (items.len() as u16 + 2).min(area.height.saturating_sub(4));
+    let rect = Rect::new((area.width - w) / 2, (area.height - h) / 2, w, h);
+    frame.render_widget(Clear, rect);
+
+    let list = List::new(items)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_set(border::PLAIN)
+            .border_style(Style::default().fg(theme.bright))
+            .title(Span::styled(" WAYBACK SNAPSHOTS ", Style::default().fg(theme.bright).add_modifier(Modifier::BOLD)))
+            .style(Style::default().bg(theme.background)))
+        .highlight_style(theme.selection())
+        .highlight_symbol("> ");
+    frame.render_stateful_widget(list, rect, &mut picker.state);
+}
+
+fn draw_help(frame: &mut Frame, area: Rect, app: &App) {
+    let theme = &app.theme;
+    let bindings = app.keymap.bindings_for(app.mode());
+    let key_width = bindings.iter()
+        .map(|(_, keys)| keys.join(" ").chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(24);
+
+    let mut lines = vec![Line::from("")];
+    for (action, keys) in &bindings {
+        let keys: String = keys.join(" ").chars().take(key_width).collect();
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {keys:>key_width$}  "), Style::default().fg(theme.bright).add_modifier(Modifier::BOLD)),
+            Span::styled(action.description(app.mode()), Style::default().fg(theme.normal)),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("  Press any key...", Style::default().fg(theme.dim))));
+
+    let w = 64.min(area.width.saturating_sub(4));
+    let h = (lines.len() as u16 + 2).min(area.height.saturating_sub(2));
+    let x = (area.width - w) / 2;
+    let y = (area.height - h) / 2;
+    let rect = Rect::new(x, y, w, h);
+
+    frame.render_widget(Clear, rect);
+
+    let title = match app.mode() {
+        Mode::Browser => " KEYS: BROWSE ",
+        Mode::Viewer => " KEYS: VIEW ",
+    };
+    let p = Paragraph::new(lines)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_set(border::PLAIN)
+            .border_style(Style::default().fg(theme.bright))
+            .title(Span::styled(title, Style::default().fg(theme.bright).add_modifier(Modifier::BOLD)))
+            .style(Style::default().bg(theme.background)));
 
     frame.render_widget(p, rect);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetcher::Response;
+    use crate::harness::{self, assert_snapshot, press, render};
+    use crate::parser::DirEntry;
+    use crate::source::{MemorySource, Metadata, Source};
+    use std::sync::Arc;
+
+    #[test]
+    fn browser_lists_directories() {
+        let mut app = harness::app(harness::site());
+        let frame = render(&mut app, 60, 6, |f, app| draw_browser(f, f.area(), app));
+        assert_snapshot("browser", &frame);
+    }
+
+    #[test]
+    fn viewer_shows_text_file() {
+        let mut app = harness::app(harness::site());
+        press(&mut app, "<Enter><Enter>");
+        assert_eq!(app.mode(), Mode::Viewer);
+        let frame = render(&mut app, 60, 9, |f, app| draw_viewer(f, f.area(), app));
+        assert_snapshot("viewer", &frame);
+    }
+
+    #[test]
+    fn back_returns_to_listing() {
+        let mut app = harness::app(harness::site());
+        press(&mut app, "<Enter>jk<Enter>");
+        assert_eq!(app.tab().browser.current_url, "http://textfiles.com/hacking/phreak.txt");
+        press(&mut app, "<BS>");
+        assert_eq!(app.mode(), Mode::Browser);
+        assert_eq!(app.tab().browser.current_url, "http://textfiles.com/hacking/");
+        assert_eq!(app.tab().selected_entry().map(|e| e.name.as_str()), Some("phreak.txt"));
+    }
+
+    #[test]
+    fn status_shows_breadcrumbs() {
+        let mut app = harness::app(harness::site());
+        press(&mut app, "<Enter>");
+        let frame = render(&mut app, 60, 2, |f, app| draw_status(f, f.area(), app));
+        assert_snapshot("status", &frame);
+    }
+
+    #[test]
+    fn missing_page_shows_error() {
+        let mut app = harness::app(harness::site());
+        press(&mut app, "j<Enter>");
+        let view = app.error.as_ref().unwrap();
+        assert_eq!(view.error.to_string(), "HTTP 404 Not Found");
+        assert_eq!(error::classify(&view.error), Some(&error::LoadError::Status(404)));
+        assert_eq!(app.suggestions(), [Suggestion::Snapshot, Suggestion::Up]);
+        let frame = render(&mut app, 60, 14, |f, app| draw_error(f, f.area(), app));
+        assert_snapshot("error", &frame);
+        press(&mut app, "x");
+        assert!(app.error.is_none());
+    }
+
+    #[test]
+    fn error_offers_going_up() {
+        let mut app = harness::app(harness::site());
+        app.navigate_to("http://textfiles.com/hacking/gone.txt").unwrap();
+        harness::settle(&mut app);
+        press(&mut app, "d");
+        assert!(app.error.as_ref().is_some_and(|v| v.details));
+        press(&mut app, "u");
+        assert!(app.error.is_none());
+        assert_eq!(app.tab().browser.current_url, "http://textfiles.com/hacking/");
+        assert_eq!(app.mode(), Mode::Browser);
+    }
+
+    /// The harness site with the network down, save for what's cached.
+    struct Offline(MemorySource);
+
+    impl Source for Offline {
+        fn metadata(&self, url: &str) -> Result<Metadata> {
+            self.0.metadata(url)
+        }
+
+        fn list(&self, url: &str) -> Result<Vec<DirEntry>> {
+            self.0.list(url)
+        }
+
+        fn read(&self, url: &str, options: ReadOptions) -> Result<Response> {
+            if options.cached || url == harness::HOME {
+                return self.0.read(url, options);
+            }
+            Err(error::LoadError::Offline("textfiles.com".into()).into())
+        }
+
+        fn is_cached(&self, url: &str) -> bool {
+            url.ends_with("phreak.txt")
+        }
+    }
+
+    #[test]
+    fn error_offers_cached_copies() {
+        let mut app = harness::app_with(Arc::new(Offline(harness::site())));
+        app.navigate_to("http://textfiles.com/hacking/unix.txt").unwrap();
+        harness::settle(&mut app);
+        assert_eq!(app.suggestions(), [Suggestion::Retry, Suggestion::Snapshot]);
+
+        app.error = None;
+        app.navigate_to("http://textfiles.com/hacking/phreak.txt").unwrap();
+        harness::settle(&mut app);
+        assert_eq!(app.suggestions(), [Suggestion::Retry, Suggestion::Cached, Suggestion::Snapshot]);
+        press(&mut app, "c");
+        assert!(app.error.is_none());
+        assert_eq!(app.tab().browser.current_url, "http://textfiles.com/hacking/phreak.txt");
+        assert_eq!(app.mode(), Mode::Viewer);
+    }
+
+    #[test]
+    fn lists_snapshots_in_the_background() {
+        let cdx = format!(
+            "https://web.archive.org/cdx/search/cdx?url={}&fl=timestamp&filter=statuscode:200&collapse=digest",
+            harness::HOME
+        );
+        let mut app = harness::app(harness::site().with(&cdx, "19990101000000\n20050101000000\n", None));
+        app.open_snapshots();
+        let loading = app.loads.first().unwrap();
+        assert_eq!(loading_message(loading), format!("Listing snapshots of {}...", harness::HOME));
+        assert!(app.snapshot_picker.is_none());
+        press(&mut app, "<Esc>");
+        assert!(app.loads.is_empty() && app.snapshot_picker.is_none());
+
+        press(&mut app, "W");
+        let picker = app.snapshot_picker.as_ref().unwrap();
+        assert_eq!(picker.snapshots, ["20050101000000", "19990101000000"]);
+        assert_eq!(picker.state.selected(), Some(0));
+    }
+
+    #[test]
+    fn tabs_load_independently() {
+        let mut app = harness::app(harness::site());
+        // Open hacking/ in a new tab while this one moves on
+        app.open_in_new_tab().unwrap();
+        app.navigate_to("http://textfiles.com/hacking/phreak.txt").unwrap();
+        assert_eq!(app.loads.len(), 2);
+        harness::settle(&mut app);
+        let urls: Vec<&str> = app.tabs.iter().map(|t| t.browser.current_url.as_str()).collect();
+        assert_eq!(urls, ["http://textfiles.com/hacking/phreak.txt", "http://textfiles.com/hacking/"]);
+
+        // A second load in the same tab replaces the first
+        app.navigate_to("http://textfiles.com/hacking/phreak.txt").unwrap();
+        app.go_back().unwrap();
+        assert_eq!(app.loads.len(), 1);
+
+        // Esc leaves other tabs' loads alone
+        harness::settle(&mut app);
+        app.switch_tab(0);
+        app.navigate_to("http://textfiles.com/hacking/").unwrap();
+        app.switch_tab(1);
+        app.navigate_to("http://textfiles.com/hacking/phreak.txt").unwrap();
+        app.handle_key(KeyEvent::from(KeyCode::Esc)).unwrap();
+        assert_eq!(app.loads.len(), 1);
+        assert_eq!(app.loads[0].destination, Destination::Tab(0, Visit::Forward));
+    }
+
+    #[test]
+    fn closing_a_tab_keeps_other_loads() {
+        let mut app = harness::app(harness::site());
+        press(&mut app, "tt");
+        assert_eq!((app.tabs.len(), app.active), (3, 2));
+        app.navigate_to("http://textfiles.com/hacking/phreak.txt").unwrap();
+        app.switch_tab(0);
+        app.navigate_to("http://textfiles.com/hacking/").unwrap();
+        app.close_tab();
+        assert_eq!(app.loads.len(), 1);
+        assert_eq!(app.loads[0].destination, Destination::Tab(1, Visit::Forward));
+        harness::settle(&mut app);
+        assert_eq!(app.tabs[1].browser.current_url, "http://textfiles.com/hacking/phreak.txt");
+        assert_eq!(app.active, 0);
+    }
+
+    #[test]
+    fn huge_counts_are_clamped() {
+        let mut app = harness::app(harness::site());
+        press(&mut app, "<Enter>99999999999999999999999j");
+        assert_eq!(app.tab().selected_entry().map(|e| e.name.as_str()), Some("unix.txt"));
+        press(&mut app, "99999999999999999999999<PageUp>99999999999999999999999<PageDown>");
+        assert_eq!(app.tab().selected_entry().map(|e| e.name.as_str()), Some("unix.txt"));
+        press(&mut app, "gg<Enter>99999999999999999999999<Tab>99999999999999999999999<C-f>");
+        assert_eq!(app.tab().focused_link, Some(0));
+    }
+
+    #[test]
+    fn hyperlinks_carry_no_escapes_of_their_own() {
+        let link = Hyperlink {
+            area: Rect::new(0, 0, 10, 1),
+            url: "http://x\x1b]0;pwned\x07".to_string(),
+            text: "x\x1b[2J\x07".to_string(),
+            style: Style::default(),
+        };
+        let mut out = Vec::new();
+        write_hyperlinks(&mut out, &[link]).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\x1b]8;;http://x]0;pwned\x1b\\x[2J\x1b]8;;\x1b\\"), "{out:?}");
+        assert!(!out.contains('\x07'));
+    }
+
+    #[test]
+    fn shows_download_progress() {
+        assert_eq!(
+            download_message("doom.zip", 3 << 20, Some(4 << 20)),
+            "Downloading doom.zip ███████████████░░░░░ 75% of 4.0 MB",
+        );
+        assert_eq!(download_message("doom.zip", 1536, None), "Downloading doom.zip: 1.5 KB");
+    }
+}
@@ -0,0 +1,233 @@
+//! Internet Archive Wayback Machine snapshots
+//!
+//! A snapshot is addressed by its Wayback URL,
+//! `<endpoint>/web/<timestamp>id_/<original url>`, so history, tabs and
+//! previews work on it like on any other page. The `id_` flag asks for the
+//! page as it was captured, without the toolbar or rewritten links.
+
+use crate::config::WaybackConfig;
+use crate::source::Sources;
+use anyhow::{Context, Result};
+
+/// Marks the start of the toolbar Wayback injects into HTML pages
+const TOOLBAR_START: &str = "<!-- BEGIN WAYBACK TOOLBAR INSERT -->";
+const TOOLBAR_END: &str = "<!-- END WAYBACK TOOLBAR INSERT -->";
+/// Scripts and styles injected at the top of `<head>`
+const REWRITE_END: &str = "<!-- End Wayback Rewrite JS Include -->";
+
+#[derive(Debug, Clone)]
+pub struct Wayback {
+    endpoint: String,
+    pub fallback: bool,
+}
+
+/// The parts of a Wayback URL
+pub struct Snapshot<'a> {
+    /// Everything before `/web/`
+    pub endpoint: &'a str,
+    pub timestamp: &'a str,
+    pub original: &'a str,
+}
+
+impl Wayback {
+    pub fn new(config: &WaybackConfig) -> Self {
+        Self { endpoint: config.endpoint.trim_end_matches('/').to_string(), fallback: config.fallback }
+    }
+
+    /// The capture of `url` nearest to `timestamp`.
+    pub fn url(&self, url: &str, timestamp: &str) -> String {
+        format!("{}/web/{timestamp}id_/{url}", self.endpoint)
+    }
+
+    /// Timestamps of the captures of `url`, oldest first, skipping ones
+    /// identical to the capture before.
+    pub fn snapshots(&self, sources: &Sources, url: &str) -> Result<Vec<String>> {
+        let query = format!(
+            "{}/cdx/search/cdx?url={}&fl=timestamp&filter=statuscode:200&collapse=digest",
+            self.endpoint,
+            escape(url),
+        );
+        let response = sources.read(&query).context("Can't list Wayback snapshots")?;
+        Ok(String::from_utf8_lossy(&response.bytes)
+            .lines()
+            .map(str::trim)
+            .filter(|l| l.len() == 14 && l.bytes().all(|b| b.is_ascii_digit()))
+            .map(String::from)
+            .collect())
+    }
+
+    /// The latest good capture of `url`, if there is one.
+    pub fn latest(&self, sources: &Sources, url: &str) -> Result<Option<String>> {
+        Ok(self.snapshots(sources, url)?.pop().map(|ts| self.url(url, &ts)))
+    }
+}
+
+/// Split a Wayback URL into its parts. Works for any host, so a local
+/// stand-in for the archive is recognised too.
+pub fn parse(url: &str) -> Option<Snapshot<'_>> {
+    let at = url.find("/web/")?;
+    let rest = &url[at + 5..];
+    let (stamp, original) = rest.split_once('/')?;
+    let digits = stamp.bytes().take_while(u8::is_ascii_digit).count();
+    // Anything after the digits is a flag like `id_` or `im_`
+    let flags = &stamp[digits..];
+    if digits == 0 || !flags.bytes().all(|b| b.is_ascii_lowercase() || b == b'_') || !original.contains("://") {
+        return None;
+    }
+    Some(Snapshot { endpoint: &url[..at], timestamp: &stamp[..digits], original })
+}
+
+/// The page `url` is a snapshot of, or `url` itself if it isn't one.
+pub fn original(url: &str) -> &str {
+    parse(url).map_or(url, |s| s.original)
+}
+
+/// The `id_` form of a Wayback URL, which serves the capture untouched.
+pub fn raw_url(url: &str) -> Option<String> {
+    let snapshot = parse(url)?;
+    Some(format!("{}/web/{}id_/{}", snapshot.endpoint, snapshot.timestamp, snapshot.original))
+}
+
+/// `url` as a query parameter, so its own `&`, `?` and `#` stay in it
+fn escape(url: &str) -> String {
+    let mut out = String::with_capacity(url.len());
+    for b in url.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~:/".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+/// `19990423101502` as `1999-04-23 10:15`
+pub fn format_timestamp(ts: &str) -> String {
+    if ts.len() < 12 {
+        return ts.to_string();
+    }
+    format!("{}-{}-{} {}:{}", &ts[0..4], &ts[4..6], &ts[6..8], &ts[8..10], &ts[10..12])
+}
+
+/// Undo what Wayback does to HTML it serves without `id_`: drop the
+/// toolbar and injected scripts, and turn links back into ones relative to
+/// the page so listings parse as they would on the live site.
+pub fn clean_html(html: &str, page_url: &str) -> String {
+    let mut html = html.to_string();
+    if let Some(end) = html.find(REWRITE_END)
+        && let Some(start) = html[..end].rfind("<script")
+    {
+        html.replace_range(start..end + REWRITE_END.len(), "");
+    }
+    if let (Some(start), Some(end)) = (html.find(TOOLBAR_START), html.find(TOOLBAR_END))
+        && start < end
+    {
+        html.replace_range(start..end + TOOLBAR_END.len(), "");
+    }
+
+    let Some(page) = parse(page_url) else {
+        return html;
+    };
+    let dir = &page.original[..page.original.rfind('/').map_or(0, |i| i + 1)];
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html.as_str();
+    // Rewritten links look like `/web/<timestamp>[flags]/<original url>`,
+    // optionally with the archive host in front
+    while let Some(at) = rest.find("/web/") {
+        let (before, after) = rest.split_at(at);
+        let start = ["https://web.archive.org", "http://web.archive.org", "//web.archive.org", page.endpoint]
+            .iter()
+            .find(|host| !host.is_empty() && before.ends_with(*host))
+            .map_or(before.len(), |host| before.len() - host.len());
+        let end = after.find(['"', '\'', '>', ' ']).unwrap_or(after.len());
+        match parse(&after[..end]) {
+            Some(link) => {
+                out.push_str(&before[..start]);
+                let original = link.original;
+                out.push_str(original.strip_prefix(dir).filter(|_| !dir.is_empty()).unwrap_or(original));
+                rest = &after[end..];
+            }
+            None => {
+                out.push_str(before);
+                out.push_str("/web/");
+                rest = &after[5..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WaybackConfig;
+    use crate::source::MemorySource;
+    use std::sync::Arc;
+
+    const ENDPOINT: &str = "http://archive.test";
+
+    /// A stand-in archive answering CDX queries for `url` with `lines`
+    fn archive(url: &str, lines: &str) -> (Wayback, Sources) {
+        let query = format!(
+            "{ENDPOINT}/cdx/search/cdx?url={}&fl=timestamp&filter=statuscode:200&collapse=digest",
+            escape(url),
+        );
+        let mut sources = Sources::default();
+        sources.register("http", Arc::new(MemorySource::new().with(&query, lines, Some("text/plain"))));
+        let wayback = Wayback::new(&WaybackConfig { endpoint: format!("{ENDPOINT}/"), fallback: true });
+        (wayback, sources)
+    }
+
+    #[test]
+    fn finds_the_latest_capture() {
+        let url = "http://textfiles.com/cgi/find.cgi?q=blue&page=2#top";
+        let (wayback, sources) = archive(url, "19990208000000\n20010516123456\n-\n2001\n");
+        assert_eq!(wayback.snapshots(&sources, url).unwrap(), ["19990208000000", "20010516123456"]);
+        assert_eq!(
+            wayback.latest(&sources, url).unwrap().as_deref(),
+            Some("http://archive.test/web/20010516123456id_/http://textfiles.com/cgi/find.cgi?q=blue&page=2#top"),
+        );
+        assert!(escape(url).ends_with("find.cgi%3Fq%3Dblue%26page%3D2%23top"));
+
+        let (wayback, sources) = archive(url, "");
+        assert_eq!(wayback.latest(&sources, url).unwrap(), None);
+        assert!(wayback.latest(&sources, "http://textfiles.com/other.txt").is_err());
+    }
+
+    #[test]
+    fn splits_snapshot_urls() {
+        let snapshot = parse("https://web.archive.org/web/19990208000000im_/http://textfiles.com/art/").unwrap();
+        assert_eq!(snapshot.endpoint, "https://web.archive.org");
+        assert_eq!(snapshot.timestamp, "19990208000000");
+        assert_eq!(snapshot.original, "http://textfiles.com/art/");
+        assert!(parse("http://textfiles.com/web/index.html").is_none());
+        assert!(parse("http://archive.test/web/1999X/http://textfiles.com/").is_none());
+        assert!(parse("http://archive.test/web/1999/textfiles.com").is_none());
+
+        assert_eq!(
+            raw_url("https://web.archive.org/web/1999/http://textfiles.com/").as_deref(),
+            Some("https://web.archive.org/web/1999id_/http://textfiles.com/"),
+        );
+        assert_eq!(raw_url("http://textfiles.com/"), None);
+        assert_eq!(format_timestamp("19990423101502"), "1999-04-23 10:15");
+    }
+
+    #[test]
+    fn strips_the_toolbar_and_rewritten_links() {
+        let page = "https://web.archive.org/web/19990208000000/http://textfiles.com/hacking/";
+        let html = r#"<html><head><script src="/_static/wombat.js"></script>
+<!-- End Wayback Rewrite JS Include -->
+<title>Hacking</title></head><body>
+<!-- BEGIN WAYBACK TOOLBAR INSERT --><div id="wm-ipp">toolbar</div><!-- END WAYBACK TOOLBAR INSERT -->
+<a href="/web/19990208000000/http://textfiles.com/hacking/phreak.txt">phreak.txt</a>
+<a href="https://web.archive.org/web/19990301000000/http://textfiles.com/humor/">humor</a>
+<a href="/web/about">about</a>
+</body></html>"#;
+        let cleaned = clean_html(html, page);
+        assert!(!cleaned.contains("wombat") && !cleaned.contains("toolbar"));
+        assert!(cleaned.contains(r#"<a href="phreak.txt">"#));
+        assert!(cleaned.contains(r#"<a href="http://textfiles.com/humor/">"#));
+        assert!(cleaned.contains(r#"<a href="/web/about">"#));
+    }
+}